@@ -0,0 +1,62 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use lib_simulation::{Simulation, SimulationConfig};
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+
+/// Compares the spatial index against the brute-force approach, for
+/// a few different world sizes
+fn step(c: &mut Criterion) {
+    let mut group = c.benchmark_group("step");
+
+    for &population in &[40, 500, 2000] {
+        for &spatial_index in &[false, true] {
+            let name = if spatial_index { "grid" } else { "brute-force" };
+
+            let config = SimulationConfig {
+                animals: population,
+                foods: population * 3 / 2,
+                spatial_index,
+                ..Default::default()
+            };
+
+            let mut rng = ChaCha8Rng::from_seed(Default::default());
+            let mut sim = Simulation::from_config(config, &mut rng).unwrap();
+
+            group.bench_with_input(BenchmarkId::new(name, population), &(), |b, _| {
+                b.iter(|| sim.step(&mut rng))
+            });
+        }
+    }
+
+    group.finish();
+}
+
+/// Measures how the step scales with the population; run it with and
+/// without `--features parallel` to see how much the threads help
+fn population(c: &mut Criterion) {
+    let mut group = c.benchmark_group("population");
+
+    // Bigger worlds take a while to simulate, so let's not overdo it
+    group.sample_size(10);
+
+    for &population in &[40, 1000, 10000] {
+        let config = SimulationConfig {
+            animals: population,
+            foods: population * 3 / 2,
+            spatial_index: true,
+            ..Default::default()
+        };
+
+        let mut rng = ChaCha8Rng::from_seed(Default::default());
+        let mut sim = Simulation::from_config(config, &mut rng).unwrap();
+
+        group.bench_with_input(BenchmarkId::from_parameter(population), &(), |b, _| {
+            b.iter(|| sim.step(&mut rng))
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, step, population);
+criterion_main!(benches);
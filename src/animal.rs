@@ -2,66 +2,274 @@ use lib_genetic_algorithm as ga;
 use nalgebra as na;
 use rand::Rng;
 
-use self::{brain::Brain, eye::Eye};
+pub use self::body::Body;
+crate use self::body::{BrainLayout, ChromosomeLayout};
+pub use self::eye::VisionChannel;
+crate use self::{
+    brain::{Brain, BrainShape},
+    eye::Eye,
+};
+use crate::{
+    AnimalId, Genome, GenomeError, LifetimeStats, PredatorConfig, SimulationConfig, Species,
+    Starvation,
+};
 
 pub use self::individual::AnimalIndividual;
 
+mod body;
 mod brain;
 mod eye;
 mod individual;
 
 #[derive(Debug)]
 pub struct Animal {
+    crate id: AnimalId,
     crate position: na::Point2<f32>,
     crate rotation: na::Rotation2<f32>,
     crate speed: f32,
+    crate body: Body,
     crate eye: Eye,
     crate brain: Brain,
-    /// Number of foods eaten by this animal
-    crate satiation: usize,
+    crate stats: LifetimeStats,
+    crate energy: f32,
+    crate state: AnimalState,
+}
+
+/// Whether a bird is still in the game
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AnimalState {
+    Alive,
+
+    /// Bird has run out of energy and remains motionless 'till the end
+    /// of its generation (see: `Starvation::Freeze`)
+    Frozen,
+
+    /// Bird has run out of energy and is no longer a part of the world
+    /// (see: `Starvation::Die`); frontends shouldn't draw it
+    Dead,
+
+    /// Bird has been caught by a predator and is no longer a part of the
+    /// world; frontends shouldn't draw it
+    Caught,
 }
 
 impl Animal {
-    fn new(eye: Eye, brain: Brain, rng: &mut dyn rand::RngCore) -> Self {
+    fn new(body: Body, eye: Eye, brain: Brain, energy: f32, rng: &mut dyn rand::RngCore) -> Self {
         Self {
+            id: AnimalId::default(),
             position: rng.gen(),
             rotation: rng.gen(),
             speed: 0.002,
+            body,
             eye,
             brain,
-            satiation: 0,
+            stats: LifetimeStats::default(),
+            energy,
+            state: AnimalState::Alive,
         }
     }
 
-    pub fn random(rng: &mut dyn rand::RngCore) -> Self {
-        let eye = Eye::default();
-        let brain = Brain::random(rng, &eye);
+    pub fn random(config: &SimulationConfig, rng: &mut dyn rand::RngCore) -> Self {
+        let body = Body::random(config, rng);
+        let shape = BrainShape::new(config.eye_cells, config.vision.len());
+        let eye = Eye::from_config(config, &body, shape.eye_cells);
+        let mut brain = Brain::random(rng, &eye, shape);
+
+        brain.evolvable = config.neuroevolution.is_some();
+
+        Self::new(body, eye, brain, Self::initial_energy(config), rng)
+    }
+
+    crate fn random_predator(config: &PredatorConfig, rng: &mut dyn rand::RngCore) -> Self {
+        let body = Body::for_predators(config);
+        let eye = Eye::for_predators(config);
+        let brain = Brain::random(rng, &eye, BrainShape::new(config.eye_cells, 1));
 
-        Self::new(eye, brain, rng)
+        Self::new(body, eye, brain, 0.0, rng)
     }
 
     /// "Restores" bird from a chromosome.
     ///
     /// We have to have access to the PRNG in here, because our
-    /// chromosomes encode only the brains - and while we restore the
-    /// bird, we have to also randomize its position, direction, etc.
-    /// (so it's stuff that wouldn't make sense to keep in the genome.)
-    crate fn from_chromosome(chromosome: ga::Chromosome, rng: &mut dyn rand::RngCore) -> Self {
-        let eye = Eye::default();
-        let brain = Brain::from_chromosome(chromosome, &eye);
+    /// chromosomes encode only the brains (and bodies) - and while we
+    /// restore the bird, we have to also randomize its position,
+    /// direction, etc. (so it's stuff that wouldn't make sense to keep
+    /// in the genome.)
+    crate fn from_chromosome(
+        chromosome: ga::Chromosome,
+        config: &SimulationConfig,
+        rng: &mut dyn rand::RngCore,
+    ) -> Self {
+        let (body, eye, brain) = Self::decode(Species::Bird, chromosome, config);
 
-        Self::new(eye, brain, rng)
+        Self::new(body, eye, brain, Self::initial_energy(config), rng)
+    }
+
+    /// Same as `::from_chromosome()`, but for predators
+    crate fn predator_from_chromosome(
+        chromosome: ga::Chromosome,
+        config: &SimulationConfig,
+        rng: &mut dyn rand::RngCore,
+    ) -> Self {
+        let (body, eye, brain) = Self::decode(Species::Predator, chromosome, config);
+
+        Self::new(body, eye, brain, 0.0, rng)
+    }
+
+    /// Decodes animal's body, eye and brain from its chromosome (see:
+    /// `ChromosomeLayout`).
+    crate fn decode(
+        species: Species,
+        chromosome: ga::Chromosome,
+        config: &SimulationConfig,
+    ) -> (Body, Eye, Brain) {
+        let layout = ChromosomeLayout::new(species, config);
+        let (body, shape, brain) = layout.split(chromosome);
+
+        let (body, eye) = match species {
+            Species::Bird => {
+                let body = Body::from_genes(body, config);
+                let eye = Eye::from_config(config, &body, shape.eye_cells);
+
+                (body, eye)
+            }
+
+            Species::Predator => {
+                let predators = config
+                    .predators
+                    .as_ref()
+                    .expect("predators should be enabled");
+
+                (
+                    Body::for_predators(predators),
+                    Eye::for_predators(predators),
+                )
+            }
+        };
+
+        let mut brain = Brain::from_chromosome(brain, &eye, shape);
+
+        brain.evolvable = matches!(layout.brain, BrainLayout::Evolvable { .. });
+
+        (body, eye, brain)
+    }
+
+    fn initial_energy(config: &SimulationConfig) -> f32 {
+        config.energy.as_ref().map_or(0.0, |energy| energy.initial)
     }
 
     crate fn as_chromosome(&self) -> ga::Chromosome {
-        // We evolve only our birds' brains, but technically there's no
-        // reason not to simulate e.g. physical properties such as size.
-        //
-        // If that was to happen, this function could be adjusted to
-        // return a longer chromosome that encodes not only the brain,
-        // but also, say, birdie's color.
+        // Body genes go first, brain follows (see: `ChromosomeLayout`);
+        // unless bodies are evolvable, there are no body genes at all -
+        // and, similarly, unless brain's structure is evolvable, there's
+        // no need to encode its shape
+        let shape = if self.brain.evolvable {
+            self.brain.shape.encode().to_vec()
+        } else {
+            Vec::new()
+        };
+
+        self.body
+            .genes()
+            .iter()
+            .copied()
+            .chain(shape)
+            .chain(self.brain.as_chromosome())
+            .collect()
+    }
+
+    /// Imports bird's brain from a genome (e.g. one exported from another
+    /// simulation).
+    pub fn from_genome(
+        genome: Genome,
+        config: &SimulationConfig,
+        rng: &mut dyn rand::RngCore,
+    ) -> Result<Self, GenomeError> {
+        genome.validate()?;
+
+        let layout = ChromosomeLayout::new(Species::Bird, config);
+
+        let shape = BrainShape {
+            eye_cells: genome.eye_cells(),
+            hidden: genome.hidden(),
+        };
+
+        let unsupported_shape = GenomeError::UnsupportedShape {
+            eye_cells: shape.eye_cells,
+            hidden: shape.hidden,
+        };
+
+        let shape_genes = match layout.brain {
+            BrainLayout::Fixed(expected) => {
+                if shape.eye_cells != expected.eye_cells {
+                    return Err(GenomeError::EyeMismatch {
+                        expected: expected.eye_cells,
+                        actual: shape.eye_cells,
+                    });
+                }
+
+                if shape != expected {
+                    return Err(unsupported_shape);
+                }
+
+                Vec::new()
+            }
+
+            BrainLayout::Evolvable {
+                max_eye_cells,
+                max_hidden,
+            } => {
+                if shape.eye_cells > max_eye_cells || shape.hidden > max_hidden {
+                    return Err(unsupported_shape);
+                }
 
-        self.brain.as_chromosome()
+                shape.encode().to_vec()
+            }
+        };
+
+        if genome.channels() != config.vision.as_slice() {
+            return Err(GenomeError::ChannelMismatch {
+                expected: config.vision.clone(),
+                actual: genome.channels().to_vec(),
+            });
+        }
+
+        if genome.body().len() != layout.body.len() {
+            return Err(GenomeError::BodyMismatch {
+                expected: layout.body.len(),
+                actual: genome.body().len(),
+            });
+        }
+
+        let chromosome = genome
+            .body()
+            .to_vec()
+            .into_iter()
+            .chain(shape_genes)
+            .chain(genome.into_genes())
+            .collect();
+
+        Ok(Self::from_chromosome(chromosome, config, rng))
+    }
+
+    /// Exports bird's brain (and body), so that it can be e.g. saved to
+    /// a file and then used to seed another simulation.
+    pub fn genome(&self) -> Genome {
+        Genome::with_hidden(
+            self.eye.cells(),
+            self.eye.channels().to_vec(),
+            self.brain.shape.hidden,
+            self.brain.as_chromosome().into_iter().collect(),
+        )
+        .expect("bird's brain should match its eye")
+        .with_body(self.body.genes().to_vec())
+    }
+
+    /// Identifies this animal; to see its parents, children and so on,
+    /// check out `World::genealogy()`
+    pub fn id(&self) -> AnimalId {
+        self.id
     }
 
     pub fn position(&self) -> na::Point2<f32> {
@@ -83,4 +291,66 @@ impl Animal {
     pub fn rotation(&self) -> na::Rotation2<f32> {
         self.rotation
     }
+
+    pub fn body(&self) -> &Body {
+        &self.body
+    }
+
+    /// Number of foods (or, in case of predators, birds) eaten by this
+    /// animal
+    pub fn satiation(&self) -> usize {
+        self.stats.food_eaten
+    }
+
+    /// What this animal has achieved so far
+    pub fn stats(&self) -> &LifetimeStats {
+        &self.stats
+    }
+
+    /// Energy left; always zero when the energy model is disabled (see:
+    /// `SimulationConfig::energy`) and for predators, which don't get
+    /// tired
+    pub fn energy(&self) -> f32 {
+        self.energy
+    }
+
+    pub fn state(&self) -> AnimalState {
+        self.state
+    }
+
+    pub fn is_alive(&self) -> bool {
+        self.state == AnimalState::Alive
+    }
+
+    /// Adds energy gained from eating a food, if the energy model is
+    /// enabled.
+    crate fn feed(&mut self, config: &SimulationConfig, nutrition: f32) {
+        if let Some(energy) = &config.energy {
+            self.energy = (self.energy + energy.food_value * nutrition).min(energy.max);
+        }
+    }
+
+    /// Subtracts energy spent on flying at given speed and turning by
+    /// given angle, starving the bird if it's run out of energy.
+    crate fn exert(&mut self, speed: f32, rotation: f32, config: &SimulationConfig) {
+        let energy = match &config.energy {
+            Some(energy) => energy,
+            None => return,
+        };
+
+        self.energy -= energy.cost(speed, rotation, self.body.size);
+
+        if let Some(body) = &config.body {
+            self.energy -= body.vision_cost * self.body.vision_area();
+        }
+
+        if self.energy <= 0.0 {
+            self.energy = 0.0;
+
+            self.state = match energy.starvation {
+                Starvation::Die => AnimalState::Dead,
+                Starvation::Freeze => AnimalState::Frozen,
+            };
+        }
+    }
 }
@@ -0,0 +1,288 @@
+use lib_genetic_algorithm as ga;
+use rand::Rng;
+use std::ops::RangeInclusive;
+
+use super::brain::BrainShape;
+use crate::body_config::BodyGene;
+use crate::{PredatorConfig, SimulationConfig, Species, VisionChannel};
+
+/// Physical traits of an animal.
+///
+/// Unless `SimulationConfig::body` is enabled, all birds share the same
+/// body, described by the config itself - and so their chromosomes
+/// don't contain any body genes.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Body {
+    genes: Vec<f32>,
+    crate fov_range: f32,
+    crate fov_angle: f32,
+    crate speed_max: f32,
+    crate size: f32,
+}
+
+impl Body {
+    crate fn random(config: &SimulationConfig, rng: &mut dyn rand::RngCore) -> Self {
+        let len = config.body.as_ref().map_or(0, |body| body.genes().len());
+        let genes = (0..len).map(|_| rng.gen_range(-1.0..=1.0)).collect();
+
+        Self::from_genes(genes, config)
+    }
+
+    crate fn from_genes(genes: Vec<f32>, config: &SimulationConfig) -> Self {
+        let mut this = Self {
+            genes: Vec::new(),
+            fov_range: config.fov_range,
+            fov_angle: config.fov_angle,
+            speed_max: config.speed_max,
+            size: 1.0,
+        };
+
+        if let Some(body) = &config.body {
+            let kinds = body.genes();
+
+            assert_eq!(kinds.len(), genes.len());
+
+            for (kind, &gene) in kinds.into_iter().zip(&genes) {
+                match kind {
+                    BodyGene::FovRange => this.fov_range = express(gene, &body.fov_range),
+                    BodyGene::FovAngle => this.fov_angle = express(gene, &body.fov_angle),
+
+                    BodyGene::SpeedMax => {
+                        this.speed_max = express(gene, body.speed_max.as_ref().unwrap())
+                    }
+
+                    BodyGene::Size => this.size = express(gene, body.size.as_ref().unwrap()),
+                }
+            }
+        }
+
+        this.genes = genes;
+        this
+    }
+
+    /// Predators' bodies don't evolve (at least for now)
+    crate fn for_predators(config: &PredatorConfig) -> Self {
+        Self {
+            genes: Vec::new(),
+            fov_range: config.fov_range,
+            fov_angle: config.fov_angle,
+            speed_max: config.speed_max,
+            size: 1.0,
+        }
+    }
+
+    crate fn genes(&self) -> &[f32] {
+        &self.genes
+    }
+
+    pub fn fov_range(&self) -> f32 {
+        self.fov_range
+    }
+
+    pub fn fov_angle(&self) -> f32 {
+        self.fov_angle
+    }
+
+    pub fn speed_max(&self) -> f32 {
+        self.speed_max
+    }
+
+    pub fn size(&self) -> f32 {
+        self.size
+    }
+
+    /// Area of the world covered by the eye (i.e. area of a circular
+    /// sector)
+    crate fn vision_area(&self) -> f32 {
+        self.fov_range * self.fov_range * self.fov_angle / 2.0
+    }
+}
+
+/// Maps a gene into given range.
+///
+/// Genes start within `<-1.0, 1.0>`, but mutations can push them further
+/// - such genes simply saturate at the range's bounds.
+fn express(gene: f32, range: &RangeInclusive<f32>) -> f32 {
+    let t = (gene.clamp(-1.0, 1.0) + 1.0) / 2.0;
+
+    range.start() + t * (range.end() - range.start())
+}
+
+/// Describes how a chromosome is split into typed segments:
+///
+/// ```text
+/// [ body genes ][ brain shape ][ brain genes (weights & biases) ]
+/// ```
+///
+/// Brain shape is present only when it's evolvable (see:
+/// `NeuroevolutionConfig`) - otherwise all brains share the same shape,
+/// known up front.
+#[derive(Clone, Debug, PartialEq)]
+crate struct ChromosomeLayout {
+    crate body: Vec<BodyGene>,
+    crate brain: BrainLayout,
+    crate channels: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+crate enum BrainLayout {
+    Fixed(BrainShape),
+    Evolvable {
+        max_eye_cells: usize,
+        max_hidden: usize,
+    },
+}
+
+impl ChromosomeLayout {
+    crate fn new(species: Species, config: &SimulationConfig) -> Self {
+        match species {
+            Species::Bird => {
+                let brain = match &config.neuroevolution {
+                    Some(neuroevolution) => BrainLayout::Evolvable {
+                        max_eye_cells: neuroevolution.max_eye_cells,
+                        max_hidden: neuroevolution.max_hidden,
+                    },
+
+                    None => {
+                        BrainLayout::Fixed(BrainShape::new(config.eye_cells, config.vision.len()))
+                    }
+                };
+
+                Self {
+                    body: config
+                        .body
+                        .as_ref()
+                        .map_or_else(Vec::new, |body| body.genes()),
+                    brain,
+                    channels: config.vision.len(),
+                }
+            }
+
+            Species::Predator => {
+                let predators = config
+                    .predators
+                    .as_ref()
+                    .expect("predators should be enabled");
+
+                Self {
+                    body: Vec::new(),
+                    brain: BrainLayout::Fixed(BrainShape::new(
+                        predators.eye_cells,
+                        PREDATOR_CHANNELS.len(),
+                    )),
+                    channels: PREDATOR_CHANNELS.len(),
+                }
+            }
+        }
+    }
+
+    /// Returns shape of the brain encoded in given chromosome, or `None`
+    /// if the chromosome doesn't describe a brain we're able to simulate
+    crate fn shape(&self, genes: &[f32]) -> Option<BrainShape> {
+        match self.brain {
+            BrainLayout::Fixed(shape) => Some(shape),
+
+            BrainLayout::Evolvable {
+                max_eye_cells,
+                max_hidden,
+            } => {
+                let shape = BrainShape::decode(genes.get(self.body.len()..)?)?;
+
+                if shape.eye_cells <= max_eye_cells && shape.hidden <= max_hidden {
+                    Some(shape)
+                } else {
+                    None
+                }
+            }
+        }
+    }
+
+    /// Number of genes that encode brain's shape
+    crate fn shape_len(&self) -> usize {
+        match self.brain {
+            BrainLayout::Fixed(_) => 0,
+            BrainLayout::Evolvable { .. } => BrainShape::GENES,
+        }
+    }
+
+    /// Returns how many genes given chromosome should have, judging by
+    /// the brain shape it describes
+    crate fn expected_len(&self, genes: &[f32]) -> Option<usize> {
+        let shape = self.shape(genes)?;
+
+        Some(self.body.len() + self.shape_len() + shape.brain_len(self.channels))
+    }
+
+    /// Splits chromosome into body genes, brain's shape and brain genes
+    crate fn split(
+        &self,
+        chromosome: ga::Chromosome,
+    ) -> (Vec<f32>, BrainShape, ga::Chromosome) {
+        let genes: Vec<_> = chromosome.into_iter().collect();
+
+        let shape = self
+            .shape(&genes)
+            .expect("chromosome should describe a valid brain");
+
+        assert_eq!(Some(genes.len()), self.expected_len(&genes));
+
+        let mut genes = genes.into_iter();
+        let body = genes.by_ref().take(self.body.len()).collect();
+        let brain = genes.skip(self.shape_len()).collect();
+
+        (body, shape, brain)
+    }
+}
+
+/// Predators see nothing but birds
+crate const PREDATOR_CHANNELS: &[VisionChannel] = &[VisionChannel::Birds];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BodyConfig;
+    use approx::assert_relative_eq;
+    use test_case::test_case;
+
+    #[test_case(-1.0, 0.1)]
+    #[test_case(0.0, 0.3)]
+    #[test_case(1.0, 0.5)]
+    #[test_case(-7.0, 0.1)]
+    #[test_case(7.0, 0.5)]
+    fn express(gene: f32, expected: f32) {
+        assert_relative_eq!(super::express(gene, &(0.1..=0.5)), expected);
+    }
+
+    #[test]
+    fn chromosome_starts_with_body_genes() {
+        let config = SimulationConfig {
+            body: Some(BodyConfig {
+                size: Some(0.5..=2.0),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let layout = ChromosomeLayout::new(Species::Bird, &config);
+
+        assert_eq!(
+            layout.body,
+            vec![BodyGene::FovRange, BodyGene::FovAngle, BodyGene::Size]
+        );
+
+        let len = layout.expected_len(&[]).unwrap();
+        let chromosome = (0..len).map(|gene| gene as f32).collect();
+        let (body, shape, brain) = layout.split(chromosome);
+
+        assert_eq!(body, vec![0.0, 1.0, 2.0]);
+        assert_eq!(brain.len(), shape.brain_len(1));
+        assert_relative_eq!(brain[0], 3.0);
+
+        let body = Body::from_genes(vec![1.0, -1.0, 0.0], &config);
+
+        assert_relative_eq!(body.fov_range(), 0.5);
+        assert_relative_eq!(body.fov_angle(), std::f32::consts::FRAC_PI_4);
+        assert_relative_eq!(body.speed_max(), config.speed_max);
+        assert_relative_eq!(body.size(), 1.25);
+    }
+}
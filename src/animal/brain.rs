@@ -6,18 +6,31 @@ use super::eye::Eye;
 #[derive(Debug)]
 pub struct Brain {
     crate nn: nn::Network,
+    crate shape: BrainShape,
+
+    /// Whether brain's shape is inherited (see: `NeuroevolutionConfig`),
+    /// and so has to be encoded in the chromosome
+    crate evolvable: bool,
 }
 
 impl Brain {
-    pub fn random(rng: &mut dyn rand::RngCore, eye: &Eye) -> Self {
+    crate fn random(rng: &mut dyn rand::RngCore, eye: &Eye, shape: BrainShape) -> Self {
         Self {
-            nn: nn::Network::random(rng, &Self::topology(eye)),
+            nn: nn::Network::random(rng, &Self::topology(eye, shape)),
+            shape,
+            evolvable: false,
         }
     }
 
-    crate fn from_chromosome(chromosome: ga::Chromosome, eye: &Eye) -> Self {
+    crate fn from_chromosome(
+        chromosome: ga::Chromosome,
+        eye: &Eye,
+        shape: BrainShape,
+    ) -> Self {
         Self {
-            nn: nn::Network::from_weights(&Self::topology(eye), chromosome),
+            nn: nn::Network::from_weights(&Self::topology(eye, shape), chromosome),
+            shape,
+            evolvable: false,
         }
     }
 
@@ -25,15 +38,93 @@ impl Brain {
         self.nn.weights().collect()
     }
 
-    fn topology(eye: &Eye) -> [nn::LayerTopology; 3] {
+    /// Returns how many genes a chromosome describing brain for an eye
+    /// with given number of inputs must have.
+    ///
+    /// (each neuron has one weight per each neuron of the previous
+    /// layer, plus bias.)
+    crate fn chromosome_len_for(inputs: usize, hidden: usize) -> usize {
+        Self::layers(inputs, hidden)
+            .windows(2)
+            .map(|layers| (layers[0] + 1) * layers[1])
+            .sum()
+    }
+
+    /// Returns number of neurons in each layer of a brain connected to
+    /// an eye with given number of inputs (i.e. cells times channels).
+    crate fn layers(inputs: usize, hidden: usize) -> [usize; 3] {
+        [inputs, hidden, 2]
+    }
+
+    /// Returns number of hidden neurons a brain has, unless its
+    /// structure is evolvable (see: `NeuroevolutionConfig`)
+    crate fn default_hidden(inputs: usize) -> usize {
+        2 * inputs
+    }
+
+    fn topology(eye: &Eye, shape: BrainShape) -> [nn::LayerTopology; 3] {
+        assert_eq!(eye.cells(), shape.eye_cells);
+
+        let [input, hidden, output] = Self::layers(eye.inputs(), shape.hidden);
+
         [
-            nn::LayerTopology {
-                neurons: eye.cells(),
-            },
-            nn::LayerTopology {
-                neurons: 2 * eye.cells(),
-            },
-            nn::LayerTopology { neurons: 2 },
+            nn::LayerTopology { neurons: input },
+            nn::LayerTopology { neurons: hidden },
+            nn::LayerTopology { neurons: output },
         ]
     }
 }
+
+/// Number of eye cells and hidden neurons of a single brain.
+///
+/// Usually it's the same for all animals of given species, but with
+/// `SimulationConfig::neuroevolution` each bird gets its own, encoded
+/// in the chromosome (see: `ChromosomeLayout`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+crate struct BrainShape {
+    crate eye_cells: usize,
+    crate hidden: usize,
+}
+
+impl BrainShape {
+    /// Number of genes that encode the shape inside a chromosome
+    crate const GENES: usize = 2;
+
+    /// Returns the shape of a brain connected to an eye with given
+    /// number of cells, each one seeing `channels` channels
+    crate fn new(eye_cells: usize, channels: usize) -> Self {
+        Self {
+            eye_cells,
+            hidden: Brain::default_hidden(eye_cells * channels),
+        }
+    }
+
+    /// Reads the shape from its genes, returning `None` if they don't
+    /// describe a sensible brain
+    crate fn decode(genes: &[f32]) -> Option<Self> {
+        let gene = |id: usize| {
+            let gene = *genes.get(id)?;
+
+            if gene >= 1.0 && gene.fract() == 0.0 && gene <= u32::MAX as f32 {
+                Some(gene as usize)
+            } else {
+                None
+            }
+        };
+
+        Some(Self {
+            eye_cells: gene(0)?,
+            hidden: gene(1)?,
+        })
+    }
+
+    crate fn encode(&self) -> [f32; Self::GENES] {
+        [self.eye_cells as f32, self.hidden as f32]
+    }
+
+    /// Number of brain genes (weights & biases) a brain of this shape
+    /// has
+    crate fn brain_len(&self, channels: usize) -> usize {
+        Brain::chromosome_len_for(self.eye_cells * channels, self.hidden)
+    }
+}
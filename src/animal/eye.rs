@@ -1,112 +1,60 @@
 use nalgebra as na;
-use std::f32::consts::{FRAC_PI_4, PI};
+use std::f32::consts::PI;
 
-use crate::Food;
+use super::body::{Body, PREDATOR_CHANNELS};
+use crate::{Food, ObstacleConfig, PredatorConfig, SimulationConfig, WorldTopology};
 
-/// How far our eye can see:
+/// Kind of things an eye can see.
 ///
-/// -----------------
-/// |               |
-/// |               |
-/// |               |
-/// |@      %      %|
-/// |               |
-/// |               |
-/// |               |
-/// -----------------
-///
-/// If @ marks our birdie and % marks food, then a FOV_RANGE of:
-///
-/// - 0.1 = 10% of the map = bird sees no foods (at least in this case)
-/// - 0.5 = 50% of the map = bird sees one of the foods
-/// - 1.0 = 100% of the map = bird sees both foods
-const FOV_RANGE: f32 = 0.25;
-
-/// How wide our eye can see.
-///
-/// If @> marks our birdie (rotated to the right) and . marks the area
-/// our birdie sees, then a FOV_ANGLE of:
-///
-/// - PI/2 = 90° =
-///   -----------------
-///   |             /.|
-///   |           /...|
-///   |         /.....|
-///   |       @>......|
-///   |         \.....|
-///   |           \...|
-///   |             \.|
-///   -----------------
-///
-/// - PI = 180° =
-///   -----------------
-///   |       |.......|
-///   |       |.......|
-///   |       |.......|
-///   |       @>......|
-///   |       |.......|
-///   |       |.......|
-///   |       |.......|
-///   -----------------
-///
-/// - 2 * PI = 360° =
-///   -----------------
-///   |...............|
-///   |...............|
-///   |...............|
-///   |.......@>......|
-///   |...............|
-///   |...............|
-///   |...............|
-///   -----------------
-///
-/// Field of view depends on both FOV_RANGE and FOV_ANGLE:
-///
-/// - FOV_RANGE=0.4, FOV_ANGLE=PI/2:
-///   -----------------
-///   |       @       |
-///   |     /.v.\     |
-///   |   /.......\   |
-///   |   ---------   |
-///   |               |
-///   |               |
-///   |               |
-///   -----------------
-///
-/// - FOV_RANGE=0.5, FOV_ANGLE=2*PI:
-///   -----------------
-///   |               |
-///   |      ---      |
-///   |     /...\     |
-///   |    |..@..|    |
-///   |     \.../     |
-///   |      ---      |
-///   |               |
-///   -----------------
-const FOV_ANGLE: f32 = PI + FRAC_PI_4;
-
-/// How much photoreceptors there are in a single eye.
-///
-/// More cells means our birds will have more "crisp" vision, allowing
-/// them to locate the food more precisely - but the trade-off is that
-/// the evolution process will then take longer, or even fail, unable
-/// to find any solution.
-///
-/// I've found values between 3~11 sufficient, with eyes having more
-/// than ~20 photoreceptors yielding progressively worse results.
-const CELLS: usize = 9;
+/// Each channel is seen through a separate set of cells, so that the
+/// brain can tell e.g. a food from a predator.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum VisionChannel {
+    Food,
+
+    /// Other birds; allows for flocking (or avoiding the competition)
+    Birds,
+
+    /// Requires `SimulationConfig::predators` to be enabled
+    Predators,
+
+    /// Distance to the nearest obstacle, as seen along the middle of
+    /// each cell; requires `SimulationConfig::obstacles` to be enabled
+    Obstacles,
+}
 
 #[derive(Debug)]
 pub struct Eye {
     fov_range: f32,
     fov_angle: f32,
     cells: usize,
+    channels: Vec<VisionChannel>,
 }
 
 impl Eye {
-    // FOV_RANGE, FOV_ANGLE & CELLS are the values we'll use during
-    // simulation - but being able to create an arbitrary eye will
-    // come handy during the testing:
+    /// Creates a bird's eye; its shape might be either inherited (if
+    /// `SimulationConfig::body` is enabled), or the same for all birds -
+    /// ditto for the number of cells (see:
+    /// `SimulationConfig::neuroevolution`)
+    crate fn from_config(config: &SimulationConfig, body: &Body, cells: usize) -> Self {
+        Self {
+            channels: config.vision.clone(),
+            ..Self::new(body.fov_range, body.fov_angle, cells)
+        }
+    }
+
+    /// Creates a predator's eye, which sees nothing but birds
+    crate fn for_predators(config: &PredatorConfig) -> Self {
+        Self {
+            channels: PREDATOR_CHANNELS.to_vec(),
+            ..Self::new(config.fov_range, config.fov_angle, config.eye_cells)
+        }
+    }
+
+    // `SimulationConfig` provides the values we'll use during simulation
+    // - but being able to create an arbitrary eye will come handy during
+    // the testing:
     fn new(fov_range: f32, fov_angle: f32, cells: usize) -> Self {
         assert!(fov_range > 0.0);
         assert!(fov_angle > 0.0);
@@ -116,6 +64,7 @@ impl Eye {
             fov_range,
             fov_angle,
             cells,
+            channels: vec![VisionChannel::Food],
         }
     }
 
@@ -123,23 +72,65 @@ impl Eye {
         self.cells
     }
 
-    pub fn process_vision(
+    /// Things the eye tells apart (e.g. foods and predators), each one
+    /// seen through a separate set of `cells`
+    pub fn channels(&self) -> &[VisionChannel] {
+        &self.channels
+    }
+
+    /// Number of values the eye feeds into the brain
+    pub fn inputs(&self) -> usize {
+        self.cells * self.channels.len()
+    }
+
+    pub fn fov_range(&self) -> f32 {
+        self.fov_range
+    }
+
+    pub fn process_vision<'a>(
         &self,
+        topology: WorldTopology,
         position: na::Point2<f32>,
         rotation: na::Rotation2<f32>,
-        foods: &[Food],
+        foods: impl IntoIterator<Item = &'a Food>,
+        obstacles: Option<&ObstacleConfig>,
+    ) -> Vec<f32> {
+        self.process_targets(
+            topology,
+            position,
+            rotation,
+            foods.into_iter().map(|food| food.position),
+            obstacles,
+        )
+    }
+
+    /// Same as `.process_vision()`, but for arbitrary things lying at
+    /// given positions - e.g. predators or birds.
+    crate fn process_targets(
+        &self,
+        topology: WorldTopology,
+        position: na::Point2<f32>,
+        rotation: na::Rotation2<f32>,
+        targets: impl IntoIterator<Item = na::Point2<f32>>,
+        obstacles: Option<&ObstacleConfig>,
     ) -> Vec<f32> {
         let mut cells = vec![0.0; self.cells];
 
-        for food in foods {
-            let vec = self.food_vec(position, food);
+        for target in targets {
+            let vec = self.target_vec(topology, position, target);
             let dist = vec.norm();
-            let angle = self.food_angle(rotation, vec);
+            let angle = self.target_angle(rotation, vec);
 
-            if !self.visible_food(dist, angle) {
+            if !self.visible_target(dist, angle) {
                 continue;
             }
 
+            // Things hidden behind an obstacle cannot be seen
+            match obstacles {
+                Some(obstacles) if obstacles.occludes(topology, position, vec) => continue,
+                _ => (),
+            }
+
             let cell = angle / self.fov_angle * (self.cells as f32);
             let cell = (cell as usize).min(cells.len() - 1);
 
@@ -151,11 +142,38 @@ impl Eye {
         cells
     }
 
-    fn food_vec(&self, position: na::Point2<f32>, food: &Food) -> na::Vector2<f32> {
-        food.position - position
+    /// Looks at obstacles: each cell casts a single ray, going through
+    /// its middle, and sees how far it gets - the closer the obstacle,
+    /// the higher the value (just like with foods).
+    crate fn process_obstacles(
+        &self,
+        topology: WorldTopology,
+        position: na::Point2<f32>,
+        rotation: na::Rotation2<f32>,
+        obstacles: &ObstacleConfig,
+    ) -> Vec<f32> {
+        (0..self.cells)
+            .map(|cell| {
+                let angle = rotation.angle() - self.fov_angle / 2.0
+                    + (cell as f32 + 0.5) / (self.cells as f32) * self.fov_angle;
+
+                let ray = na::Rotation2::new(angle) * na::Vector2::new(self.fov_range, 0.0);
+
+                1.0 - obstacles.clearance(topology, position, ray)
+            })
+            .collect()
+    }
+
+    fn target_vec(
+        &self,
+        topology: WorldTopology,
+        position: na::Point2<f32>,
+        target: na::Point2<f32>,
+    ) -> na::Vector2<f32> {
+        topology.displacement(position, target)
     }
 
-    fn food_angle(&self, rotation: na::Rotation2<f32>, vec: na::Vector2<f32>) -> f32 {
+    fn target_angle(&self, rotation: na::Rotation2<f32>, vec: na::Vector2<f32>) -> f32 {
         let angle = na::Rotation2::rotation_between(&na::Vector2::x(), &vec).angle();
         let angle = angle - rotation.angle();
         let angle = na::wrap(angle, -PI, PI);
@@ -164,20 +182,15 @@ impl Eye {
         angle
     }
 
-    fn visible_food(&self, dist: f32, angle: f32) -> bool {
+    fn visible_target(&self, dist: f32, angle: f32) -> bool {
         dist < self.fov_range && angle >= 0.0 && angle <= self.fov_angle
     }
 }
 
-impl Default for Eye {
-    fn default() -> Self {
-        Self::new(FOV_RANGE, FOV_ANGLE, CELLS)
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use crate::food::Food;
+    use crate::WorldTopology;
     use nalgebra as na;
 
     use super::Eye;
@@ -201,6 +214,9 @@ mod tests {
 
     struct TestCase {
         foods: Vec<Food>,
+        /// Most of our tests use `WorldTopology::Infinite`, so that the
+        /// world's edges don't get in the way
+        topology: WorldTopology,
         fov_range: f32,
         fov_angle: f32,
         x: f32,
@@ -214,9 +230,11 @@ mod tests {
             let eye = Eye::new(self.fov_range, self.fov_angle, TEST_EYE_CELLS);
 
             let actual_vision = eye.process_vision(
+                self.topology,
                 na::Point2::new(self.x, self.y),
                 na::Rotation2::new(self.rot),
                 &self.foods,
+                None,
             );
 
             // The finish line!
@@ -268,12 +286,16 @@ mod tests {
     /// A helper-function that allows to create food easily
     fn food(x: f32, y: f32) -> Food {
         Food {
+            id: Default::default(),
             position: na::Point2::new(x, y),
+            nutrition: 1.0,
+            regrowth: 0,
+            dormant: false,
         }
     }
 
     mod different_fov_ranges {
-        use super::{food, TestCase};
+        use super::{food, TestCase, WorldTopology};
         use std::f32::consts::FRAC_PI_2;
         use test_case::test_case;
 
@@ -332,6 +354,7 @@ mod tests {
         #[test_case(0.1, "             ")]
         fn with_range(fov_range: f32, expected_vision: &'static str) {
             TestCase {
+                topology: WorldTopology::Infinite,
                 foods: vec![food(1.0, 0.5)],
                 fov_angle: FRAC_PI_2,
                 x: 0.5,
@@ -345,7 +368,7 @@ mod tests {
     }
 
     mod different_rotations {
-        use super::{food, TestCase};
+        use super::{food, TestCase, WorldTopology};
         use std::f32::consts::PI;
         use test_case::test_case;
 
@@ -399,6 +422,7 @@ mod tests {
         #[test_case(2.50 * PI, "      +      ")] // prove the numbers wrap.)
         fn with_rotation(rot: f32, expected_vision: &'static str) {
             TestCase {
+                topology: WorldTopology::Infinite,
                 foods: vec![food(0.5, 1.0)],
                 fov_range: 1.0,
                 fov_angle: 2.0 * PI,
@@ -412,7 +436,7 @@ mod tests {
     }
 
     mod different_positions {
-        use super::{food, TestCase};
+        use super::{food, TestCase, WorldTopology};
         use std::f32::consts::FRAC_PI_2;
         use test_case::test_case;
 
@@ -495,6 +519,7 @@ mod tests {
         #[test_case(0.5, 1.0, "+            ")]
         fn with_position(x: f32, y: f32, expected_vision: &'static str) {
             TestCase {
+                topology: WorldTopology::Infinite,
                 foods: vec![food(1.0, 0.4), food(1.0, 0.6)],
                 fov_range: 1.0,
                 fov_angle: FRAC_PI_2,
@@ -508,7 +533,7 @@ mod tests {
     }
 
     mod different_fov_angles {
-        use super::{food, TestCase};
+        use super::{food, TestCase, WorldTopology};
         use std::f32::consts::PI;
         use test_case::test_case;
 
@@ -551,6 +576,7 @@ mod tests {
         #[test_case(2.00 * PI, "+.  .+ +.  .+")] // FOV is wide = 8 foods
         fn with_angle(fov_angle: f32, expected_vision: &'static str) {
             TestCase {
+                topology: WorldTopology::Infinite,
                 foods: vec![
                     food(0.0, 0.0),
                     food(0.0, 0.33),
@@ -571,4 +597,96 @@ mod tests {
             .run()
         }
     }
+
+    mod different_topologies {
+        use super::{food, TestCase, WorldTopology};
+        use std::f32::consts::FRAC_PI_2;
+        use test_case::test_case;
+
+        /// World:
+        ///
+        /// ------------
+        /// |          |
+        /// |          |
+        /// |%        @>
+        /// |          |
+        /// |          |
+        /// ------------
+        ///
+        /// Our birdie is looking right at the edge - whether it sees the
+        /// food depends on what's on the other side:
+        #[test_case(WorldTopology::Torus, "      #      ")] // The food!
+        #[test_case(WorldTopology::Bounded, "             ")] // A wall
+        #[test_case(WorldTopology::Infinite, "             ")] // Emptiness
+        fn with_topology(topology: WorldTopology, expected_vision: &'static str) {
+            TestCase {
+                topology,
+                foods: vec![food(0.01, 0.5)],
+                fov_range: 0.1,
+                fov_angle: FRAC_PI_2,
+                x: 0.99,
+                y: 0.5,
+                rot: 0.0,
+                expected_vision,
+            }
+            .run()
+        }
+    }
+
+    mod obstacles {
+        use super::{convert_vision, food, Eye, TEST_EYE_CELLS};
+        use crate::{Obstacle, ObstacleConfig, WorldTopology};
+        use approx::assert_relative_eq;
+        use nalgebra as na;
+        use std::f32::consts::FRAC_PI_2;
+
+        /// World:
+        ///
+        /// ------------
+        /// |      |   |
+        /// |      |   |
+        /// |    @>|  %|
+        /// |      |   |
+        /// |      |   |
+        /// ------------
+        ///
+        /// There's a wall between our birdie and the food:
+        #[test]
+        fn hide_food() {
+            let eye = Eye::new(1.0, FRAC_PI_2, TEST_EYE_CELLS);
+
+            let obstacles = ObstacleConfig {
+                shapes: vec![Obstacle::Wall {
+                    from: [0.75, 0.0],
+                    to: [0.75, 1.0],
+                }],
+                ..Default::default()
+            };
+
+            let vision = |obstacles| {
+                convert_vision(eye.process_vision(
+                    WorldTopology::Infinite,
+                    na::Point2::new(0.5, 0.5),
+                    na::Rotation2::new(0.0),
+                    &[food(1.0, 0.5)],
+                    obstacles,
+                ))
+            };
+
+            assert_eq!(vision(None), "      +      ");
+            assert_eq!(vision(Some(&obstacles)), "             ");
+
+            // ... although the birdie can see the wall itself, if it
+            // wants to
+            let vision = eye.process_obstacles(
+                WorldTopology::Infinite,
+                na::Point2::new(0.5, 0.5),
+                na::Rotation2::new(0.0),
+                &obstacles,
+            );
+
+            assert_relative_eq!(vision[TEST_EYE_CELLS / 2], 0.75, epsilon = 1e-6);
+            assert!(vision.iter().all(|&cell| cell > 0.0 && cell <= 0.75));
+        }
+    }
 }
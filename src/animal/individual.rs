@@ -1,22 +1,73 @@
 use lib_genetic_algorithm as ga;
 
-use crate::Animal;
+use crate::{Animal, AnimalId, FitnessFunction, Genealogy, SimulationConfig};
 
+#[derive(Clone)]
 pub struct AnimalIndividual {
     fitness: f32,
     chromosome: ga::Chromosome,
+
+    /// Id of the animal this individual has been created from; `None`
+    /// for children that haven't been born yet
+    id: Option<AnimalId>,
+
+    /// Parents of a child that hasn't been born yet (see: `Genealogy`)
+    parents: Option<[AnimalId; 2]>,
 }
 
 impl AnimalIndividual {
-    pub fn from_animal(animal: &Animal) -> Self {
+    pub fn from_animal(animal: &Animal, fitness: &dyn FitnessFunction) -> Self {
         Self {
-            fitness: animal.satiation as f32,
+            // Roulette wheel cannot deal with negative fitness
+            fitness: fitness.fitness(&animal.stats).max(0.0),
             chromosome: animal.as_chromosome(),
+            id: Some(animal.id),
+            parents: None,
         }
     }
 
-    pub fn into_animal(self, rng: &mut dyn rand::RngCore) -> Animal {
-        Animal::from_chromosome(self.chromosome, rng)
+    /// Creates an individual for a child of given parents
+    pub fn child_of(chromosome: ga::Chromosome, parents: [&Self; 2]) -> Self {
+        Self {
+            parents: match parents {
+                [Self { id: Some(a), .. }, Self { id: Some(b), .. }] => Some([*a, *b]),
+                _ => None,
+            },
+            ..ga::Individual::create(chromosome)
+        }
+    }
+
+    pub fn into_animal(
+        self,
+        config: &SimulationConfig,
+        genealogy: &mut Genealogy,
+        rng: &mut dyn rand::RngCore,
+    ) -> Animal {
+        Animal {
+            id: self.id_in(genealogy),
+            ..Animal::from_chromosome(self.chromosome, config, rng)
+        }
+    }
+
+    pub fn into_predator(
+        self,
+        config: &SimulationConfig,
+        genealogy: &mut Genealogy,
+        rng: &mut dyn rand::RngCore,
+    ) -> Animal {
+        Animal {
+            id: self.id_in(genealogy),
+            ..Animal::predator_from_chromosome(self.chromosome, config, rng)
+        }
+    }
+
+    /// Returns id of the animal this individual has been created from or,
+    /// if it's a child, registers its birth
+    fn id_in(&self, genealogy: &mut Genealogy) -> AnimalId {
+        match self.id {
+            Some(id) => id,
+            None => genealogy.birth(self.parents),
+        }
     }
 }
 
@@ -25,6 +76,8 @@ impl ga::Individual for AnimalIndividual {
         Self {
             fitness: 0.0,
             chromosome,
+            id: None,
+            parents: None,
         }
     }
 
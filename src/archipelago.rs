@@ -0,0 +1,345 @@
+use crate::config::ensure;
+use crate::{parallel, ConfigError, Genome, Simulation, SimulationConfig, Statistics};
+
+/// A bunch of simulations ("islands") evolving side by side, every now
+/// and then exchanging their fittest birds - the so-called island model.
+///
+/// Each island lives in its own world and has its own RNG, so running an
+/// archipelago is pretty much like running a couple of seeds at once -
+/// except that good ideas get to spread from one island to another,
+/// while each island still keeps its own flavour of them.
+///
+/// With the `parallel` feature enabled, islands get trained on many
+/// threads at once; either way, the results are the same.
+pub struct Archipelago<R: rand::RngCore + Send> {
+    islands: Vec<Island<R>>,
+    migration: MigrationConfig,
+
+    /// Number of generations since the last migration
+    since_migration: usize,
+}
+
+impl<R: rand::RngCore + Send> Archipelago<R> {
+    /// Creates an archipelago with one island per each of given RNGs -
+    /// e.g. `(0..12).map(ChaCha8Rng::seed_from_u64)` yields twelve
+    /// islands, seeded from 0 up to 11.
+    pub fn new(
+        config: SimulationConfig,
+        migration: MigrationConfig,
+        rngs: impl IntoIterator<Item = R>,
+    ) -> Result<Self, ConfigError> {
+        config.validate()?;
+
+        let islands: Vec<_> = rngs
+            .into_iter()
+            .map(|mut rng| Island {
+                simulation: Simulation::from_config(config.clone(), &mut rng)
+                    .expect("config has been already validated"),
+                rng,
+                stats: None,
+                emigrants: Vec::new(),
+            })
+            .collect();
+
+        ensure(!islands.is_empty(), "islands", "must not be empty")?;
+        migration.validate(&config, islands.len())?;
+
+        Ok(Self {
+            islands,
+            migration,
+            since_migration: 0,
+        })
+    }
+
+    /// Returns all of the islands, in the order their RNGs were given
+    pub fn islands(&self) -> impl Iterator<Item = &Simulation> {
+        self.islands.iter().map(|island| &island.simulation)
+    }
+
+    pub fn migration(&self) -> &MigrationConfig {
+        &self.migration
+    }
+
+    /// Trains each island for a generation (see: `Simulation::train()`),
+    /// letting the birds migrate afterwards, if it's the time.
+    pub fn train(&mut self) -> ArchipelagoStatistics {
+        self.since_migration += 1;
+
+        let migrants = if self.since_migration >= self.migration.interval {
+            self.since_migration = 0;
+            self.migration.migrants
+        } else {
+            0
+        };
+
+        parallel::for_each_mut(&mut self.islands, |island| {
+            let (stats, emigrants) = island.simulation.train_and_pick(migrants, &mut island.rng);
+
+            island.stats = Some(stats);
+            island.emigrants = emigrants;
+        });
+
+        // Migration happens on a single thread, island after island, so
+        // that it doesn't matter how many threads there were
+        if migrants > 0 {
+            let count = self.islands.len();
+
+            for target in 0..count {
+                let immigrants: Vec<_> = self
+                    .migration
+                    .topology
+                    .sources(target, count)
+                    .into_iter()
+                    .flat_map(|source| self.islands[source].emigrants.clone())
+                    .collect();
+
+                let island = &mut self.islands[target];
+
+                island
+                    .simulation
+                    .welcome(immigrants, &mut island.rng)
+                    .expect("islands share the config, so their genomes should be compatible");
+            }
+        }
+
+        let islands: Vec<_> = self
+            .islands
+            .iter_mut()
+            .map(|island| {
+                island.emigrants.clear();
+                island.stats.clone().expect("island should've been trained")
+            })
+            .collect();
+
+        ArchipelagoStatistics {
+            global: summarize(&islands),
+            islands,
+        }
+    }
+}
+
+struct Island<R> {
+    simulation: Simulation,
+    rng: R,
+
+    /// Statistics of the most recent generation
+    stats: Option<Statistics>,
+
+    /// Birds that are about to leave this island
+    emigrants: Vec<Genome>,
+}
+
+/// How birds move between the islands (see: `Archipelago`)
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct MigrationConfig {
+    pub topology: MigrationTopology,
+
+    /// Every how many generations birds migrate
+    pub interval: usize,
+
+    /// How many of the fittest birds leave each island when it's time to
+    /// migrate; they don't really leave, though - rather, copies of them
+    /// replace the weakest birds at the destination.
+    pub migrants: usize,
+}
+
+impl Default for MigrationConfig {
+    fn default() -> Self {
+        Self {
+            topology: MigrationTopology::Ring,
+            interval: 10,
+            migrants: 2,
+        }
+    }
+}
+
+impl MigrationConfig {
+    crate fn validate(
+        &self,
+        config: &SimulationConfig,
+        islands: usize,
+    ) -> Result<(), ConfigError> {
+        ensure(self.interval > 0, "migration.interval", "must be positive")?;
+
+        let immigrants = self.migrants * self.topology.sources(0, islands).len();
+
+        ensure(
+            immigrants <= config.animals,
+            "migration.migrants",
+            "islands cannot take in more birds than they hold",
+        )
+    }
+}
+
+/// Which islands send birds to which (see: `Archipelago`)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MigrationTopology {
+    /// Each island sends birds to the next one, with the last island
+    /// sending them back to the first one; ideas spread slowly, so the
+    /// islands stay diverse for longer
+    Ring,
+
+    /// Each island sends birds to all of the other ones; ideas spread
+    /// fast, but the islands tend to become alike
+    FullyConnected,
+}
+
+impl MigrationTopology {
+    /// Returns islands that send their birds to the `target`-th one
+    fn sources(self, target: usize, count: usize) -> Vec<usize> {
+        match self {
+            Self::Ring if count > 1 => vec![(target + count - 1) % count],
+            Self::Ring => Vec::new(),
+            Self::FullyConnected => (0..count).filter(|&source| source != target).collect(),
+        }
+    }
+}
+
+/// Summary of a single generation of an archipelago
+#[derive(Clone, Debug, PartialEq)]
+pub struct ArchipelagoStatistics {
+    islands: Vec<Statistics>,
+    global: Statistics,
+}
+
+impl ArchipelagoStatistics {
+    /// Statistics of each island, in the same order as
+    /// `Archipelago::islands()`
+    pub fn islands(&self) -> &[Statistics] {
+        &self.islands
+    }
+
+    /// Statistics of all of the islands taken together, as if they were
+    /// a single population
+    pub fn global(&self) -> &Statistics {
+        &self.global
+    }
+}
+
+/// Merges statistics of many islands into one.
+///
+/// (since all of the islands share the config, they're all equally
+/// populated - so the global average is simply the average of averages.)
+fn summarize(islands: &[Statistics]) -> Statistics {
+    let count = islands.len() as f32;
+
+    let max_fitness = islands
+        .iter()
+        .map(Statistics::max_fitness)
+        .fold(f32::NEG_INFINITY, f32::max);
+
+    let best_fitness_ever = islands
+        .iter()
+        .map(Statistics::best_fitness_ever)
+        .fold(f32::NEG_INFINITY, f32::max);
+
+    let predators: Option<Vec<_>> = islands
+        .iter()
+        .map(|island| island.predators().cloned())
+        .collect();
+
+    Statistics {
+        generation: islands[0].generation,
+        min_fitness: islands
+            .iter()
+            .map(Statistics::min_fitness)
+            .fold(f32::INFINITY, f32::min),
+        max_fitness,
+        avg_fitness: islands.iter().map(Statistics::avg_fitness).sum::<f32>() / count,
+        best_fitness_ever,
+        regressed: max_fitness < best_fitness_ever,
+        predators: predators.map(|predators| Box::new(summarize(&predators))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+    use test_case::test_case;
+
+    #[test_case(MigrationTopology::Ring, 0, vec![3])]
+    #[test_case(MigrationTopology::Ring, 2, vec![1])]
+    #[test_case(MigrationTopology::FullyConnected, 0, vec![1, 2, 3])]
+    #[test_case(MigrationTopology::FullyConnected, 2, vec![0, 1, 3])]
+    fn sources(topology: MigrationTopology, target: usize, expected: Vec<usize>) {
+        assert_eq!(topology.sources(target, 4), expected);
+    }
+
+    #[test]
+    fn emigrants_are_judged_like_statistics() {
+        let config = SimulationConfig {
+            animals: 20,
+            generation_length: 50,
+            ..Default::default()
+        };
+
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let mut sim = Simulation::from_config(config, &mut rng).unwrap();
+
+        for _ in 0..5 {
+            let (stats, emigrants) = sim.train_and_pick(1, &mut rng);
+
+            // The emigrant is the bird that `Statistics` considers the
+            // fittest one, as it was when the generation ended
+            let emigrant = sim
+                .retired
+                .iter()
+                .find(|bird| bird.genome() == emigrants[0])
+                .unwrap();
+
+            assert_eq!(
+                sim.fitness.fitness(&emigrant.stats).max(0.0),
+                stats.max_fitness()
+            );
+        }
+    }
+
+    #[test]
+    fn migrates_the_fittest_birds() {
+        let config = SimulationConfig {
+            animals: 10,
+            generation_length: 50,
+            ..Default::default()
+        };
+
+        let migration = MigrationConfig {
+            topology: MigrationTopology::Ring,
+            interval: 1,
+            migrants: 2,
+        };
+
+        let mut archipelago = Archipelago::new(
+            config.clone(),
+            migration,
+            (0..3).map(ChaCha8Rng::seed_from_u64),
+        )
+        .unwrap();
+
+        // Let's replay what happens on the first island by hand, to know
+        // which birds should emigrate from it
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let mut island = Simulation::from_config(config, &mut rng).unwrap();
+        let (_, emigrants) = island.train_and_pick(2, &mut rng);
+
+        let stats = archipelago.train();
+        let second = archipelago.islands().nth(1).unwrap().genomes();
+
+        for emigrant in emigrants {
+            assert!(second.contains(&emigrant));
+        }
+
+        let global = stats.global();
+
+        assert_eq!(stats.islands().len(), 3);
+
+        for island in stats.islands() {
+            assert!(global.min_fitness() <= island.min_fitness());
+            assert!(global.max_fitness() >= island.max_fitness());
+        }
+    }
+}
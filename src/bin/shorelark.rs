@@ -0,0 +1,508 @@
+//! Headless runner - trains a simulation for given number of generations
+//! and prints how the fitness evolves, so that experiments can be driven
+//! from scripts instead of the browser:
+//!
+//! ```text
+//! $ shorelark --config config.json --seed 42 --generations 500 --format csv
+//! ```
+//!
+//! Training can be resumed from a checkpoint (see: `--checkpoint-every`
+//! and `--resume`); checkpoints contain both the simulation and the RNG,
+//! so a resumed run ends up exactly where an uninterrupted one would've.
+//!
+//! Exit codes:
+//!
+//! - 0 - everything's fine,
+//! - 1 - invalid command-line arguments,
+//! - 2 - invalid configuration (or checkpoint),
+//! - 3 - I/O error (e.g. config file doesn't exist).
+
+use lib_simulation::{Simulation, SimulationConfig, Snapshot, Statistics};
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::{env, fmt, fs, io, process};
+
+const USAGE: &str = "\
+Usage: shorelark [OPTIONS]
+
+Options:
+  --config <PATH>            Simulation config, as JSON (missing fields get
+                             their default values); defaults to the default
+                             config
+  --resume <PATH>            Checkpoint to resume training from (cannot be
+                             combined with --config)
+  --seed <NUMBER>            RNG seed [default: 0]; ignored when resuming,
+                             since checkpoints contain the RNG's state
+  --generations <NUMBER>     Number of generations to train [default: 100]
+  --format <FORMAT>          Output format: table, csv or jsonl
+                             [default: table]
+  --checkpoint-every <N>     Save a checkpoint every N generations
+  --checkpoint-dir <PATH>    Where to save the checkpoints [default: .]
+  -h, --help                 Print this message
+";
+
+fn main() {
+    let result = match Args::parse(env::args().skip(1)) {
+        Ok(Some(args)) => run(args, &mut io::stdout().lock()),
+
+        Ok(None) => {
+            print!("{}", USAGE);
+            Ok(())
+        }
+
+        Err(err) => Err(err),
+    };
+
+    if let Err(err) = result {
+        eprintln!("shorelark: {}", err);
+
+        if let Error::Usage(_) = err {
+            eprintln!();
+            eprint!("{}", USAGE);
+        }
+
+        process::exit(err.exit_code());
+    }
+}
+
+fn run(args: Args, out: &mut impl Write) -> Result<(), Error> {
+    let (mut simulation, mut rng) = match (&args.config, &args.resume) {
+        (_, Some(path)) => {
+            let checkpoint: Checkpoint = read_json(path)?;
+
+            let simulation = Simulation::restore(checkpoint.simulation)
+                .map_err(|err| Error::Config(format!("{}: {}", path.display(), err)))?;
+
+            (simulation, checkpoint.rng)
+        }
+
+        (Some(path), None) => {
+            let config: SimulationConfig = read_json(path)?;
+            let mut rng = ChaCha8Rng::seed_from_u64(args.seed);
+
+            let simulation = Simulation::from_config(config, &mut rng)
+                .map_err(|err| Error::Config(format!("{}: {}", path.display(), err)))?;
+
+            (simulation, rng)
+        }
+
+        (None, None) => {
+            let mut rng = ChaCha8Rng::seed_from_u64(args.seed);
+
+            let simulation = Simulation::from_config(SimulationConfig::default(), &mut rng)
+                .map_err(|err| Error::Config(err.to_string()))?;
+
+            (simulation, rng)
+        }
+    };
+
+    args.format.print_header(out)?;
+
+    let mut since_checkpoint = 0;
+
+    for _ in 0..args.generations {
+        let stats = simulation.train(&mut rng);
+
+        args.format.print(out, &stats)?;
+        out.flush()?;
+
+        since_checkpoint += 1;
+
+        if Some(since_checkpoint) == args.checkpoint_every {
+            let path = args
+                .checkpoint_dir
+                .join(format!("generation-{:05}.json", stats.generation()));
+
+            let checkpoint = Checkpoint {
+                simulation: simulation.snapshot(),
+                rng: rng.clone(),
+            };
+
+            write_json(&path, &checkpoint)?;
+            since_checkpoint = 0;
+        }
+    }
+
+    Ok(())
+}
+
+/// Everything that's needed to resume training (see: `--resume`) - not
+/// only the simulation, but also the RNG that drives it
+#[derive(Serialize, Deserialize)]
+struct Checkpoint {
+    simulation: Snapshot,
+    rng: ChaCha8Rng,
+}
+
+struct Args {
+    config: Option<PathBuf>,
+    resume: Option<PathBuf>,
+    seed: u64,
+    generations: usize,
+    format: Format,
+    checkpoint_every: Option<usize>,
+    checkpoint_dir: PathBuf,
+}
+
+impl Args {
+    /// Parses command-line arguments; returns `None` when the user has
+    /// asked for help.
+    ///
+    /// (we've got only a handful of flags, so pulling in a whole argument
+    /// parsing library didn't seem worth it.)
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Option<Self>, Error> {
+        let mut this = Self {
+            config: None,
+            resume: None,
+            seed: 0,
+            generations: 100,
+            format: Format::Table,
+            checkpoint_every: None,
+            checkpoint_dir: PathBuf::from("."),
+        };
+
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .ok_or_else(|| Error::Usage(format!("{} requires a value", arg)))
+            };
+
+            match arg.as_str() {
+                "-h" | "--help" => return Ok(None),
+                "--config" => this.config = Some(value()?.into()),
+                "--resume" => this.resume = Some(value()?.into()),
+                "--seed" => this.seed = parse_number(&arg, value()?)?,
+                "--generations" => this.generations = parse_number(&arg, value()?)?,
+                "--format" => this.format = Format::parse(&value()?)?,
+                "--checkpoint-every" => {
+                    this.checkpoint_every = match parse_number(&arg, value()?)? {
+                        0 => return Err(Error::Usage(format!("{} must be positive", arg))),
+                        every => Some(every),
+                    }
+                }
+                "--checkpoint-dir" => this.checkpoint_dir = value()?.into(),
+                _ => return Err(Error::Usage(format!("unknown argument: {}", arg))),
+            }
+        }
+
+        if this.config.is_some() && this.resume.is_some() {
+            return Err(Error::Usage(
+                "--config and --resume cannot be used together (checkpoints contain their own config)"
+                    .into(),
+            ));
+        }
+
+        Ok(Some(this))
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(arg: &str, value: String) -> Result<T, Error> {
+    value
+        .parse()
+        .map_err(|_| Error::Usage(format!("{} expects a number, got: {}", arg, value)))
+}
+
+#[derive(Clone, Copy)]
+enum Format {
+    /// Human-readable, aligned columns
+    Table,
+
+    Csv,
+
+    /// One JSON object per line
+    JsonLines,
+}
+
+impl Format {
+    fn parse(format: &str) -> Result<Self, Error> {
+        match format {
+            "table" => Ok(Self::Table),
+            "csv" => Ok(Self::Csv),
+            "jsonl" => Ok(Self::JsonLines),
+            _ => Err(Error::Usage(format!("unknown format: {}", format))),
+        }
+    }
+
+    fn print_header(self, out: &mut impl Write) -> io::Result<()> {
+        match self {
+            Self::Table => writeln!(
+                out,
+                "{:>10}  {:>12}  {:>12}  {:>12}  {:>12}",
+                "generation", "min fitness", "max fitness", "avg fitness", "best ever"
+            ),
+
+            Self::Csv => writeln!(
+                out,
+                "generation,min_fitness,max_fitness,avg_fitness,best_fitness_ever"
+            ),
+
+            Self::JsonLines => Ok(()),
+        }
+    }
+
+    fn print(self, out: &mut impl Write, stats: &Statistics) -> io::Result<()> {
+        match self {
+            Self::Table => writeln!(
+                out,
+                "{:>10}  {:>12.3}  {:>12.3}  {:>12.3}  {:>12.3}",
+                stats.generation(),
+                stats.min_fitness(),
+                stats.max_fitness(),
+                stats.avg_fitness(),
+                stats.best_fitness_ever(),
+            ),
+
+            Self::Csv => writeln!(
+                out,
+                "{},{},{},{},{}",
+                stats.generation(),
+                stats.min_fitness(),
+                stats.max_fitness(),
+                stats.avg_fitness(),
+                stats.best_fitness_ever(),
+            ),
+
+            Self::JsonLines => {
+                let line = serde_json::json!({
+                    "generation": stats.generation(),
+                    "min_fitness": stats.min_fitness(),
+                    "max_fitness": stats.max_fitness(),
+                    "avg_fitness": stats.avg_fitness(),
+                    "best_fitness_ever": stats.best_fitness_ever(),
+                });
+
+                writeln!(out, "{}", line)
+            }
+        }
+    }
+}
+
+fn read_json<T: serde::de::DeserializeOwned>(path: &Path) -> Result<T, Error> {
+    let json = fs::read_to_string(path)
+        .map_err(|err| Error::Io(format!("couldn't read {}: {}", path.display(), err)))?;
+
+    serde_json::from_str(&json).map_err(|err| Error::Config(format!("{}: {}", path.display(), err)))
+}
+
+fn write_json(path: &Path, value: &impl Serialize) -> Result<(), Error> {
+    let file = fs::File::create(path)
+        .map_err(|err| Error::Io(format!("couldn't create {}: {}", path.display(), err)))?;
+
+    serde_json::to_writer(io::BufWriter::new(file), value)
+        .map_err(|err| Error::Io(format!("couldn't write {}: {}", path.display(), err)))
+}
+
+enum Error {
+    Usage(String),
+    Config(String),
+    Io(String),
+}
+
+impl Error {
+    fn exit_code(&self) -> i32 {
+        match self {
+            Self::Usage(_) => 1,
+            Self::Config(_) => 2,
+            Self::Io(_) => 3,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err.to_string())
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Usage(msg) | Self::Config(msg) | Self::Io(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    fn parse(args: &[&str]) -> Result<Option<Args>, Error> {
+        Args::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn parses_arguments() {
+        let args = parse(&[
+            "--seed",
+            "42",
+            "--generations",
+            "5",
+            "--format",
+            "csv",
+            "--checkpoint-every",
+            "2",
+        ]);
+
+        match args {
+            Ok(Some(args)) => {
+                assert_eq!(args.seed, 42);
+                assert_eq!(args.generations, 5);
+                assert!(matches!(args.format, Format::Csv));
+                assert_eq!(args.checkpoint_every, Some(2));
+                assert_eq!(args.checkpoint_dir, PathBuf::from("."));
+            }
+
+            _ => panic!("arguments should've been parsed"),
+        }
+
+        assert!(matches!(parse(&["--seed", "1", "--help"]), Ok(None)));
+    }
+
+    #[test_case(&["--frobnicate"], "unknown argument: --frobnicate")]
+    #[test_case(&["--seed"], "--seed requires a value")]
+    #[test_case(&["--seed", "many"], "--seed expects a number, got: many")]
+    #[test_case(&["--format", "xml"], "unknown format: xml")]
+    #[test_case(&["--checkpoint-every", "0"], "--checkpoint-every must be positive")]
+    #[test_case(
+        &["--config", "config.json", "--resume", "snapshot.json"],
+        "--config and --resume cannot be used together (checkpoints contain their own config)"
+    )]
+    fn rejects_invalid_arguments(args: &[&str], expected: &str) {
+        match parse(args) {
+            Err(Error::Usage(msg)) => assert_eq!(msg, expected),
+            _ => panic!("arguments should've been rejected"),
+        }
+    }
+
+    #[test]
+    fn prints_statistics() {
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+
+        let config = SimulationConfig {
+            animals: 5,
+            generation_length: 20,
+            ..Default::default()
+        };
+
+        let stats = Simulation::from_config(config, &mut rng)
+            .unwrap()
+            .train(&mut rng);
+
+        let print = |format: Format| {
+            let mut out = Vec::new();
+
+            format.print_header(&mut out).unwrap();
+            format.print(&mut out, &stats).unwrap();
+
+            String::from_utf8(out).unwrap()
+        };
+
+        assert_eq!(
+            print(Format::Csv),
+            format!(
+                "generation,min_fitness,max_fitness,avg_fitness,best_fitness_ever\n{},{},{},{},{}\n",
+                stats.generation(),
+                stats.min_fitness(),
+                stats.max_fitness(),
+                stats.avg_fitness(),
+                stats.best_fitness_ever(),
+            )
+        );
+
+        // Header and values should line up
+        let table = print(Format::Table);
+        let lines: Vec<_> = table.lines().collect();
+
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].len(), lines[1].len());
+        assert_eq!(lines[1].split_whitespace().count(), 5);
+
+        let json: serde_json::Value = serde_json::from_str(&print(Format::JsonLines)).unwrap();
+
+        assert_eq!(json["generation"], stats.generation());
+        assert_eq!(json["min_fitness"], stats.min_fitness());
+        assert_eq!(json["max_fitness"], stats.max_fitness());
+        assert_eq!(json["avg_fitness"], stats.avg_fitness());
+        assert_eq!(json["best_fitness_ever"], stats.best_fitness_ever());
+    }
+
+    #[test]
+    fn resumes_exactly_where_it_left_off() {
+        let dir = env::temp_dir().join(format!("shorelark-{}", process::id()));
+
+        fs::create_dir_all(&dir).unwrap();
+
+        let config = dir.join("config.json");
+
+        fs::write(&config, r#"{ "animals": 5, "generation_length": 20 }"#).unwrap();
+
+        let train = |args: &[&str]| {
+            let args = parse(args).ok().flatten().unwrap();
+            let mut out = Vec::new();
+
+            if run(args, &mut out).is_err() {
+                panic!("training should've succeeded");
+            }
+
+            String::from_utf8(out).unwrap()
+        };
+
+        let config = config.to_str().unwrap();
+        let dir_str = dir.to_str().unwrap();
+
+        let uninterrupted = train(&[
+            "--config",
+            config,
+            "--seed",
+            "7",
+            "--generations",
+            "5",
+            "--format",
+            "csv",
+        ]);
+
+        let first = train(&[
+            "--config",
+            config,
+            "--seed",
+            "7",
+            "--generations",
+            "2",
+            "--format",
+            "csv",
+            "--checkpoint-every",
+            "2",
+            "--checkpoint-dir",
+            dir_str,
+        ]);
+
+        let checkpoint = dir.join("generation-00001.json");
+
+        let second = train(&[
+            "--resume",
+            checkpoint.to_str().unwrap(),
+            "--generations",
+            "3",
+            "--format",
+            "csv",
+        ]);
+
+        fs::remove_dir_all(&dir).unwrap();
+
+        let resumed: Vec<_> = first.lines().chain(second.lines().skip(1)).collect();
+
+        assert_eq!(uninterrupted.lines().collect::<Vec<_>>(), resumed);
+    }
+
+    #[test_case(Error::Usage("unknown argument".into()), 1)]
+    #[test_case(Error::Config("invalid config".into()), 2)]
+    #[test_case(Error::Io("couldn't read".into()), 3)]
+    #[test_case(io::Error::from(io::ErrorKind::NotFound).into(), 3)]
+    fn exit_code(err: Error, expected: i32) {
+        assert_eq!(err.exit_code(), expected);
+    }
+}
@@ -0,0 +1,131 @@
+use std::f32::consts::{FRAC_PI_4, PI};
+use std::ops::RangeInclusive;
+
+use crate::config::ensure;
+use crate::ConfigError;
+
+/// Makes birds' bodies evolvable: instead of sharing the same eyes, each
+/// bird inherits its `fov_range` and `fov_angle` (and, optionally, its
+/// maximum speed and size) from its parents.
+///
+/// Each trait is encoded as a single gene, which then gets mapped into
+/// given range - e.g. with `fov_range: 0.1..=0.5`, a gene of -1.0 (or
+/// lower) means an eye that sees 0.1 of the map, while a gene of 1.0 (or
+/// higher) means an eye that sees half of the map.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct BodyConfig {
+    /// Range of evolvable `SimulationConfig::fov_range`
+    pub fov_range: RangeInclusive<f32>,
+
+    /// Range of evolvable `SimulationConfig::fov_angle`
+    pub fov_angle: RangeInclusive<f32>,
+
+    /// Range of evolvable `SimulationConfig::speed_max`; when `None`,
+    /// all birds share the same maximum speed
+    pub speed_max: Option<RangeInclusive<f32>>,
+
+    /// Range of evolvable body size; when `None`, all birds are of size
+    /// 1.0.
+    ///
+    /// Larger birds reach further (their `eat_range` gets multiplied by
+    /// their size), but flying and turning costs them proportionally
+    /// more energy.
+    pub size: Option<RangeInclusive<f32>>,
+
+    /// How much energy a bird spends per step per unit of the area its
+    /// eye covers - so that wide, long-range eyes don't come for free.
+    ///
+    /// Requires the energy model (see: `SimulationConfig::energy`) - a
+    /// non-zero cost without it gets rejected, as there'd be no energy
+    /// to pay it with.
+    pub vision_cost: f32,
+}
+
+impl BodyConfig {
+    crate fn validate(&self, speed_min: f32, energy: bool) -> Result<(), ConfigError> {
+        ensure(
+            is_valid_range(&self.fov_range) && *self.fov_range.start() > 0.0,
+            "body.fov_range",
+            "must be a finite, non-empty range of positive values",
+        )?;
+
+        ensure(
+            is_valid_range(&self.fov_angle)
+                && *self.fov_angle.start() > 0.0
+                && *self.fov_angle.end() <= 2.0 * PI,
+            "body.fov_angle",
+            "must be a non-empty range within (0, 2*PI]",
+        )?;
+
+        if let Some(speed_max) = &self.speed_max {
+            ensure(
+                is_valid_range(speed_max) && *speed_max.start() >= speed_min,
+                "body.speed_max",
+                "must be a finite, non-empty range of values not lower than speed_min",
+            )?;
+        }
+
+        if let Some(size) = &self.size {
+            ensure(
+                is_valid_range(size) && *size.start() > 0.0,
+                "body.size",
+                "must be a finite, non-empty range of positive values",
+            )?;
+        }
+
+        ensure(
+            self.vision_cost.is_finite() && self.vision_cost >= 0.0,
+            "body.vision_cost",
+            "must be finite and non-negative",
+        )?;
+
+        ensure(
+            self.vision_cost == 0.0 || energy,
+            "body.vision_cost",
+            "requires the energy model",
+        )
+    }
+
+    /// Returns genes that make up a bird's body, in the order they appear
+    /// in the chromosome
+    crate fn genes(&self) -> Vec<BodyGene> {
+        let mut genes = vec![BodyGene::FovRange, BodyGene::FovAngle];
+
+        if self.speed_max.is_some() {
+            genes.push(BodyGene::SpeedMax);
+        }
+
+        if self.size.is_some() {
+            genes.push(BodyGene::Size);
+        }
+
+        genes
+    }
+}
+
+impl Default for BodyConfig {
+    fn default() -> Self {
+        Self {
+            fov_range: 0.1..=0.5,
+            fov_angle: FRAC_PI_4..=(2.0 * PI),
+            speed_max: None,
+            size: None,
+            vision_cost: 0.0,
+        }
+    }
+}
+
+/// A single physical trait encoded in the chromosome
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+crate enum BodyGene {
+    FovRange,
+    FovAngle,
+    SpeedMax,
+    Size,
+}
+
+fn is_valid_range(range: &RangeInclusive<f32>) -> bool {
+    range.start().is_finite() && range.end().is_finite() && range.start() <= range.end()
+}
@@ -0,0 +1,621 @@
+use std::f32::consts::{FRAC_PI_2, FRAC_PI_4, PI};
+use std::{error, fmt};
+
+use crate::{
+    BodyConfig, Crossover, EnergyConfig, EvolutionMode, Fitness, FoodConfig, Mutation,
+    NeuroevolutionConfig, ObstacleConfig, Placement, PredatorConfig, Selection, VisionChannel,
+    WorldTopology,
+};
+
+/// All the knobs that affect how our simulation behaves.
+///
+/// Defaults are the values I've found to work nicely - but since they
+/// were (mostly) chosen with a fair dice roll, feel free to experiment.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct SimulationConfig {
+    /// Minimum speed of a bird.
+    ///
+    /// Keeping it above zero prevents birds from getting stuck in one
+    /// place.
+    pub speed_min: f32,
+
+    /// Maximum speed of a bird.
+    ///
+    /// Keeping it "sane" prevents birds from accelerating up to infinity,
+    /// which makes the simulation... unrealistic :-)
+    pub speed_max: f32,
+
+    /// Speed acceleration; determines how much the brain can affect
+    /// bird's speed during one step.
+    ///
+    /// Assuming our bird is currently flying with speed=0.5, when the
+    /// brain yells "stop flying!", a `speed_accel` of:
+    ///
+    /// - 0.1 = makes it take 5 steps ("5 seconds") for the bird to
+    ///   actually slow down to `speed_min`,
+    ///
+    /// - 0.5 = makes it take 1 step for the bird to slow down to
+    ///   `speed_min`.
+    ///
+    /// This improves simulation faithfulness, because - as in real life -
+    /// it's not possible to increase speed from 1km/h to 50km/h in one
+    /// instant, even if your brain very much wants to.
+    pub speed_accel: f32,
+
+    /// Ditto, but for rotation:
+    ///
+    /// - 2 * PI = it takes one step for the bird to do a 360° rotation,
+    /// - PI = it takes two steps for the bird to do a 360° rotation,
+    ///
+    /// I've chosen PI/2, because - as our motto goes - this value seems
+    /// to play nice.
+    pub rotation_accel: f32,
+
+    /// How much `.step()`-s have to occur before we push data into the
+    /// genetic algorithm.
+    ///
+    /// Value that's too low might prevent the birds from learning, while
+    /// a value that's too high will make the evolution unnecessarily
+    /// slower.
+    ///
+    /// You can treat this number as "for how many steps each bird gets
+    /// to live"; 2500 was chosen with a fair dice roll.
+    pub generation_length: usize,
+
+    /// How far our eye can see:
+    ///
+    /// -----------------
+    /// |               |
+    /// |               |
+    /// |               |
+    /// |@      %      %|
+    /// |               |
+    /// |               |
+    /// |               |
+    /// -----------------
+    ///
+    /// If @ marks our birdie and % marks food, then a `fov_range` of:
+    ///
+    /// - 0.1 = 10% of the map = bird sees no foods (at least in this case)
+    /// - 0.5 = 50% of the map = bird sees one of the foods
+    /// - 1.0 = 100% of the map = bird sees both foods
+    pub fov_range: f32,
+
+    /// How wide our eye can see.
+    ///
+    /// If @> marks our birdie (rotated to the right) and . marks the area
+    /// our birdie sees, then a `fov_angle` of:
+    ///
+    /// - PI/2 = 90° =
+    ///   -----------------
+    ///   |             /.|
+    ///   |           /...|
+    ///   |         /.....|
+    ///   |       @>......|
+    ///   |         \.....|
+    ///   |           \...|
+    ///   |             \.|
+    ///   -----------------
+    ///
+    /// - PI = 180° =
+    ///   -----------------
+    ///   |       |.......|
+    ///   |       |.......|
+    ///   |       |.......|
+    ///   |       @>......|
+    ///   |       |.......|
+    ///   |       |.......|
+    ///   |       |.......|
+    ///   -----------------
+    ///
+    /// - 2 * PI = 360° =
+    ///   -----------------
+    ///   |...............|
+    ///   |...............|
+    ///   |...............|
+    ///   |.......@>......|
+    ///   |...............|
+    ///   |...............|
+    ///   |...............|
+    ///   -----------------
+    ///
+    /// Field of view depends on both `fov_range` and `fov_angle`:
+    ///
+    /// - fov_range=0.4, fov_angle=PI/2:
+    ///   -----------------
+    ///   |       @       |
+    ///   |     /.v.\     |
+    ///   |   /.......\   |
+    ///   |   ---------   |
+    ///   |               |
+    ///   |               |
+    ///   |               |
+    ///   -----------------
+    ///
+    /// - fov_range=0.5, fov_angle=2*PI:
+    ///   -----------------
+    ///   |               |
+    ///   |      ---      |
+    ///   |     /...\     |
+    ///   |    |..@..|    |
+    ///   |     \.../     |
+    ///   |      ---      |
+    ///   |               |
+    ///   -----------------
+    pub fov_angle: f32,
+
+    /// How much photoreceptors there are in a single eye.
+    ///
+    /// More cells means our birds will have more "crisp" vision, allowing
+    /// them to locate the food more precisely - but the trade-off is that
+    /// the evolution process will then take longer, or even fail, unable
+    /// to find any solution.
+    ///
+    /// I've found values between 3~11 sufficient, with eyes having more
+    /// than ~20 photoreceptors yielding progressively worse results.
+    pub eye_cells: usize,
+
+    /// What birds are able to see, each channel through its own set of
+    /// `eye_cells`.
+    ///
+    /// By default birds see nothing but food; seeing e.g. other birds
+    /// allows for flocking to emerge, but - since each channel makes the
+    /// brain larger - it also makes the evolution take longer.
+    pub vision: Vec<VisionChannel>,
+
+    /// What happens at the edges of the world; affects movement,
+    /// collisions and vision alike
+    pub topology: WorldTopology,
+
+    /// How many birds live in the world
+    pub animals: usize,
+
+    /// How many foods are scattered around the world
+    pub foods: usize,
+
+    /// How birds get laid out on the map - at the beginning of each
+    /// generation, or as they're born (see: `EvolutionMode::SteadyState`)
+    pub bird_placement: Placement,
+
+    /// How foods get laid out on the map - at the beginning of each
+    /// generation, and as they regrow after being eaten (unless they grow
+    /// in patches, see: `FoodDistribution::Patches`)
+    pub food_placement: Placement,
+
+    /// How foods grow, get eaten and regrow; when disabled (which is the
+    /// default), eaten food immediately reappears somewhere else
+    pub food: Option<FoodConfig>,
+
+    /// How close a bird has to get to a food in order to eat it
+    pub eat_range: f32,
+
+    /// Whether collisions and vision should look up foods through a
+    /// spatial index instead of checking every food, one by one.
+    ///
+    /// Both approaches yield exactly the same results, but the index
+    /// makes large worlds (think: thousands of birds and foods) much
+    /// faster; the brute-force approach is kept mostly for comparison.
+    pub spatial_index: bool,
+
+    /// Whether the population gets replaced all at once, at the end of
+    /// each generation, or bird by bird
+    pub evolution_mode: EvolutionMode,
+
+    /// How birds are judged at the end of each generation
+    pub fitness: Fitness,
+
+    /// How parents get picked for the next generation
+    pub selection: Selection,
+
+    /// How parents get combined into children
+    pub crossover: Crossover,
+
+    /// How children get mutated
+    pub mutation: Mutation,
+
+    /// Number of the fittest birds that get to live another generation
+    /// unchanged - this way a champion cannot get lost because of an
+    /// unlucky crossover or mutation.
+    ///
+    /// Doesn't apply to `EvolutionMode::SteadyState`, where birds live
+    /// for as long as they can anyway.
+    pub elitism: usize,
+
+    /// Energy model; when disabled (which is the default), birds fly
+    /// forever, regardless of whether they eat or not
+    pub energy: Option<EnergyConfig>,
+
+    /// Predators hunting the birds; requires `vision` to contain
+    /// `VisionChannel::Predators`, so that birds can see them coming
+    pub predators: Option<PredatorConfig>,
+
+    /// Static obstacles birds cannot fly nor see through; note that birds
+    /// don't see the obstacles themselves unless `vision` contains
+    /// `VisionChannel::Obstacles`
+    pub obstacles: Option<ObstacleConfig>,
+
+    /// Evolvable bodies; when enabled, `fov_range` and `fov_angle` (and,
+    /// optionally, `speed_max`) become heritable traits instead of being
+    /// shared by all birds
+    pub body: Option<BodyConfig>,
+
+    /// Evolvable brain structure; when enabled, birds' brains (and eyes)
+    /// can grow and shrink, instead of all having the same shape
+    pub neuroevolution: Option<NeuroevolutionConfig>,
+}
+
+impl SimulationConfig {
+    /// Checks whether this configuration describes a world we're able to
+    /// simulate.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        ensure(self.speed_min >= 0.0, "speed_min", "must be non-negative")?;
+
+        ensure(
+            self.speed_max.is_finite() && self.speed_max >= self.speed_min,
+            "speed_max",
+            "must be finite and not lower than speed_min",
+        )?;
+
+        ensure(
+            self.speed_accel.is_finite() && self.speed_accel >= 0.0,
+            "speed_accel",
+            "must be finite and non-negative",
+        )?;
+
+        ensure(
+            self.rotation_accel.is_finite() && self.rotation_accel >= 0.0,
+            "rotation_accel",
+            "must be finite and non-negative",
+        )?;
+
+        ensure(
+            self.generation_length > 0,
+            "generation_length",
+            "must be positive",
+        )?;
+
+        ensure(
+            self.fov_range.is_finite() && self.fov_range > 0.0,
+            "fov_range",
+            "must be finite and positive",
+        )?;
+
+        ensure(
+            self.fov_angle > 0.0 && self.fov_angle <= 2.0 * PI,
+            "fov_angle",
+            "must be within (0, 2*PI]",
+        )?;
+
+        ensure(self.eye_cells > 0, "eye_cells", "must be positive")?;
+        ensure(!self.vision.is_empty(), "vision", "must not be empty")?;
+
+        for (id, channel) in self.vision.iter().enumerate() {
+            ensure(
+                !self.vision[..id].contains(channel),
+                "vision",
+                "must not contain duplicates",
+            )?;
+        }
+
+        ensure(
+            self.predators.is_some() || !self.vision.contains(&VisionChannel::Predators),
+            "vision",
+            "cannot see predators when there are none",
+        )?;
+
+        ensure(
+            self.obstacles.is_some() || !self.vision.contains(&VisionChannel::Obstacles),
+            "vision",
+            "cannot see obstacles when there are none",
+        )?;
+
+        ensure(self.animals > 0, "animals", "must be positive")?;
+
+        ensure(
+            self.eat_range.is_finite() && self.eat_range >= 0.0,
+            "eat_range",
+            "must be finite and non-negative",
+        )?;
+
+        self.bird_placement.validate("bird_placement")?;
+        self.food_placement.validate("food_placement")?;
+
+        self.evolution_mode.validate()?;
+        self.selection.validate()?;
+        self.crossover.validate()?;
+        self.mutation.validate()?;
+
+        ensure(
+            self.elitism <= self.animals,
+            "elitism",
+            "cannot exceed the number of animals",
+        )?;
+
+        if let Some(energy) = &self.energy {
+            energy.validate()?;
+        }
+
+        if let Some(predators) = &self.predators {
+            predators.validate()?;
+
+            // Birds that cannot see predators wouldn't learn to escape
+            // them, so both species wouldn't co-evolve - that's rather a
+            // mistake than something one would want on purpose
+            ensure(
+                self.vision.contains(&VisionChannel::Predators),
+                "vision",
+                "must contain predators when there are some",
+            )?;
+        }
+
+        if let Some(obstacles) = &self.obstacles {
+            obstacles.validate()?;
+        }
+
+        if let Some(body) = &self.body {
+            body.validate(self.speed_min, self.energy.is_some())?;
+        }
+
+        if let Some(food) = &self.food {
+            food.validate()?;
+        }
+
+        if let Some(neuroevolution) = &self.neuroevolution {
+            neuroevolution.validate(self.eye_cells, self.vision.len())?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for SimulationConfig {
+    fn default() -> Self {
+        Self {
+            speed_min: 0.001,
+            speed_max: 0.005,
+            speed_accel: 0.2,
+            rotation_accel: FRAC_PI_2,
+            generation_length: 2500,
+            fov_range: 0.25,
+            fov_angle: PI + FRAC_PI_4,
+            eye_cells: 9,
+            vision: vec![VisionChannel::Food],
+            topology: WorldTopology::default(),
+            animals: 40,
+            foods: 60,
+            bird_placement: Placement::default(),
+            food_placement: Placement::default(),
+            food: None,
+            eat_range: 0.01,
+            spatial_index: true,
+            evolution_mode: EvolutionMode::default(),
+            fitness: Fitness::default(),
+            selection: Selection::default(),
+            crossover: Crossover::default(),
+            mutation: Mutation::default(),
+            elitism: 0,
+            energy: None,
+            predators: None,
+            obstacles: None,
+            body: None,
+            neuroevolution: None,
+        }
+    }
+}
+
+/// Describes why given `SimulationConfig` got rejected
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConfigError {
+    field: &'static str,
+    reason: &'static str,
+}
+
+impl ConfigError {
+    /// Name of the offending field, e.g. `speed_max`
+    pub fn field(&self) -> &'static str {
+        self.field
+    }
+
+    pub fn reason(&self) -> &'static str {
+        self.reason
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid `{}`: {}", self.field, self.reason)
+    }
+}
+
+impl error::Error for ConfigError {
+    //
+}
+
+crate fn ensure(
+    condition: bool,
+    field: &'static str,
+    reason: &'static str,
+) -> Result<(), ConfigError> {
+    if condition {
+        Ok(())
+    } else {
+        Err(ConfigError { field, reason })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FoodDistribution, Obstacle};
+
+    #[test]
+    fn default_config_is_valid() {
+        assert_eq!(SimulationConfig::default().validate(), Ok(()));
+    }
+
+    #[test]
+    fn rejects_invalid_values() {
+        type Tweak = fn(&mut SimulationConfig);
+
+        let cases: &[(Tweak, &str)] = &[
+            (|c| c.speed_min = -0.1, "speed_min"),
+            (|c| c.speed_max = 0.0005, "speed_max"),
+            (|c| c.speed_max = f32::INFINITY, "speed_max"),
+            (|c| c.speed_accel = f32::NAN, "speed_accel"),
+            (|c| c.rotation_accel = -1.0, "rotation_accel"),
+            (|c| c.generation_length = 0, "generation_length"),
+            (|c| c.fov_range = 0.0, "fov_range"),
+            (|c| c.fov_angle = 7.0, "fov_angle"),
+            (|c| c.eye_cells = 0, "eye_cells"),
+            (|c| c.vision = vec![], "vision"),
+            (
+                |c| c.vision = vec![VisionChannel::Food, VisionChannel::Food],
+                "vision",
+            ),
+            (|c| c.vision = vec![VisionChannel::Predators], "vision"),
+            (|c| c.vision = vec![VisionChannel::Obstacles], "vision"),
+            (|c| c.predators = Some(Default::default()), "vision"),
+            (|c| c.animals = 0, "animals"),
+            (|c| c.elitism = 41, "elitism"),
+            (|c| c.eat_range = -0.01, "eat_range"),
+            (
+                |c| c.bird_placement = Placement::GridJitter { jitter: 1.5 },
+                "bird_placement",
+            ),
+            (
+                |c| c.food_placement = Placement::Custom(vec![[0.5, 2.0]]),
+                "food_placement",
+            ),
+            (
+                |c| {
+                    c.food = Some(FoodConfig {
+                        distribution: FoodDistribution::Patches {
+                            count: 0,
+                            radius: 0.1,
+                            drift: 0.0,
+                        },
+                        ..Default::default()
+                    })
+                },
+                "food.distribution.count",
+            ),
+            (
+                |c| {
+                    c.energy = Some(EnergyConfig {
+                        initial: 2.0,
+                        ..Default::default()
+                    })
+                },
+                "energy.initial",
+            ),
+            (
+                |c| {
+                    c.predators = Some(PredatorConfig {
+                        count: 0,
+                        ..Default::default()
+                    })
+                },
+                "predators.count",
+            ),
+            (
+                |c| {
+                    c.obstacles = Some(ObstacleConfig {
+                        shapes: vec![Obstacle::Circle {
+                            center: [0.5, 0.5],
+                            radius: 0.0,
+                        }],
+                        ..Default::default()
+                    })
+                },
+                "obstacles.shapes",
+            ),
+            (
+                |c| {
+                    c.body = Some(BodyConfig {
+                        fov_range: 0.5..=0.1,
+                        ..Default::default()
+                    })
+                },
+                "body.fov_range",
+            ),
+            (
+                |c| {
+                    c.body = Some(BodyConfig {
+                        vision_cost: 0.01,
+                        ..Default::default()
+                    })
+                },
+                "body.vision_cost",
+            ),
+            (
+                |c| {
+                    c.neuroevolution = Some(NeuroevolutionConfig {
+                        max_hidden: 17,
+                        ..Default::default()
+                    })
+                },
+                "neuroevolution.max_hidden",
+            ),
+            (
+                |c| {
+                    c.neuroevolution = Some(NeuroevolutionConfig {
+                        add_neuron_chance: -0.1,
+                        ..Default::default()
+                    })
+                },
+                "neuroevolution.add_neuron_chance",
+            ),
+            (
+                |c| c.evolution_mode = EvolutionMode::SteadyState { max_age: 0 },
+                "evolution_mode.max_age",
+            ),
+            (
+                |c| c.selection = Selection::Tournament { size: 0 },
+                "selection.size",
+            ),
+            (
+                |c| c.crossover = Crossover::MultiPoint { points: 0 },
+                "crossover.points",
+            ),
+            (
+                |c| {
+                    c.mutation = Mutation::Gaussian {
+                        chance: 1.5,
+                        coeff: 0.3,
+                    }
+                },
+                "mutation.chance",
+            ),
+            (
+                |c| {
+                    c.mutation = Mutation::Gaussian {
+                        chance: 0.01,
+                        coeff: -0.3,
+                    }
+                },
+                "mutation.coeff",
+            ),
+            (
+                |c| {
+                    c.mutation = Mutation::Adaptive {
+                        chance: 0.01,
+                        coeff: 0.3,
+                        min_coeff: 0.1,
+                        max_coeff: 0.2,
+                        factor: 1.5,
+                    }
+                },
+                "mutation.coeff",
+            ),
+        ];
+
+        for (tweak, field) in cases {
+            let mut config = SimulationConfig::default();
+            tweak(&mut config);
+
+            assert_eq!(config.validate().unwrap_err().field(), *field);
+        }
+    }
+}
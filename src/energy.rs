@@ -0,0 +1,194 @@
+use crate::config::ensure;
+use crate::ConfigError;
+
+/// Energy model: flying and turning drain bird's energy, eating restores
+/// it - and a bird that runs out of energy is out of the game for the
+/// rest of its generation.
+///
+/// Without it, birds fly forever regardless of whether they eat or not,
+/// so there's no real pressure on foraging efficiently; with it, the
+/// lazy ones simply don't live long enough to pass their genes.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct EnergyConfig {
+    /// How much energy each bird is born with
+    pub initial: f32,
+
+    /// How much energy a bird can store at most; anything eaten above
+    /// that goes to waste
+    pub max: f32,
+
+    /// How much energy a single food restores
+    pub food_value: f32,
+
+    /// How much energy a bird spends per step just for being alive
+    pub base_cost: f32,
+
+    /// How much energy a bird spends per step per unit of speed.
+    ///
+    /// Since speeds are tiny (see: `SimulationConfig::speed_max`), so is
+    /// the amount of energy spent on flying - e.g. with the defaults, a
+    /// bird flying at full speed burns 0.0005 per step.
+    pub speed_cost: f32,
+
+    /// How much energy a bird spends per step per radian turned
+    pub turning_cost: f32,
+
+    /// What happens to a bird that runs out of energy
+    pub starvation: Starvation,
+}
+
+impl EnergyConfig {
+    /// Returns how much energy a bird of given size, flying with given
+    /// speed and turning by given angle, spends during one step.
+    crate fn cost(&self, speed: f32, rotation: f32, size: f32) -> f32 {
+        self.base_cost + size * self.speed_cost * speed + size * self.turning_cost * rotation.abs()
+    }
+
+    crate fn validate(&self) -> Result<(), ConfigError> {
+        ensure(
+            self.max.is_finite() && self.max > 0.0,
+            "energy.max",
+            "must be finite and positive",
+        )?;
+
+        ensure(
+            self.initial > 0.0 && self.initial <= self.max,
+            "energy.initial",
+            "must be within (0, max]",
+        )?;
+
+        ensure(
+            self.food_value.is_finite() && self.food_value >= 0.0,
+            "energy.food_value",
+            "must be finite and non-negative",
+        )?;
+
+        for &(cost, field) in &[
+            (self.base_cost, "energy.base_cost"),
+            (self.speed_cost, "energy.speed_cost"),
+            (self.turning_cost, "energy.turning_cost"),
+        ] {
+            ensure(
+                cost.is_finite() && cost >= 0.0,
+                field,
+                "must be finite and non-negative",
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for EnergyConfig {
+    fn default() -> Self {
+        Self {
+            initial: 1.0,
+            max: 1.0,
+            food_value: 0.25,
+            base_cost: 0.0002,
+            speed_cost: 0.1,
+            turning_cost: 0.0005,
+            starvation: Starvation::default(),
+        }
+    }
+}
+
+/// What happens to a bird that runs out of energy.
+///
+/// Either way the bird stops flying, eating and aging (so its `lifetime`
+/// tells for how long it's managed to survive) - the only difference is
+/// whether it remains visible in the world.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Starvation {
+    /// Bird disappears from the world
+    Die,
+
+    /// Bird remains in the world, motionless
+    Freeze,
+}
+
+impl Default for Starvation {
+    fn default() -> Self {
+        Self::Die
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AnimalState, Simulation, SimulationConfig};
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+    use test_case::test_case;
+
+    fn config(starvation: Starvation, foods: usize) -> SimulationConfig {
+        SimulationConfig {
+            animals: 10,
+            foods,
+            energy: Some(EnergyConfig {
+                initial: 0.05,
+                max: 0.1,
+                food_value: 0.05,
+                base_cost: 0.001,
+                starvation,
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test_case(Starvation::Die, AnimalState::Dead)]
+    #[test_case(Starvation::Freeze, AnimalState::Frozen)]
+    fn starving_birds_stop(starvation: Starvation, expected: AnimalState) {
+        let mut rng = ChaCha8Rng::from_seed(Default::default());
+        let mut sim = Simulation::from_config(config(starvation, 0), &mut rng).unwrap();
+
+        for _ in 0..100 {
+            sim.step(&mut rng);
+        }
+
+        let positions: Vec<_> = sim.world().animals().iter().map(|a| a.position()).collect();
+
+        for _ in 0..10 {
+            sim.step(&mut rng);
+        }
+
+        for (animal, position) in sim.world().animals().iter().zip(positions) {
+            assert_eq!(animal.state(), expected);
+            assert_eq!(animal.energy(), 0.0);
+            assert_eq!(animal.position(), position);
+            assert!(animal.stats().lifetime() < 50);
+        }
+    }
+
+    #[test]
+    fn food_restores_energy() {
+        let mut rng = ChaCha8Rng::from_seed(Default::default());
+
+        let mut sim = Simulation::from_config(
+            SimulationConfig {
+                eat_range: 0.05,
+                ..config(Starvation::Die, 500)
+            },
+            &mut rng,
+        )
+        .unwrap();
+
+        for _ in 0..200 {
+            sim.step(&mut rng);
+        }
+
+        for animal in sim.world().animals() {
+            assert!(animal.energy() <= 0.1);
+        }
+
+        assert!(sim
+            .world()
+            .animals()
+            .iter()
+            .any(|animal| animal.state() == AnimalState::Alive));
+    }
+}
@@ -0,0 +1,682 @@
+use lib_genetic_algorithm as ga;
+use lib_genetic_algorithm::{CrossoverMethod, MutationMethod, SelectionMethod};
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use crate::config::ensure;
+use crate::neuroevolution::{StructuralCrossover, StructuralGenes, StructuralMutation};
+use crate::{ConfigError, Statistics};
+
+/// How the population gets renewed
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum EvolutionMode {
+    /// Every `generation_length` steps, the entire population gets
+    /// replaced by offspring - all at once
+    Generational,
+
+    /// Birds die one by one - of old age (after living for `max_age`
+    /// steps) or of starvation (see: `SimulationConfig::energy`) - and
+    /// each one gets immediately replaced by an offspring of two of the
+    /// currently living birds.
+    ///
+    /// There are no generation boundaries in this mode; instead of
+    /// generation-level statistics, `Simulation::step()` reports births
+    /// and deaths.
+    SteadyState { max_age: usize },
+}
+
+impl Default for EvolutionMode {
+    fn default() -> Self {
+        Self::Generational
+    }
+}
+
+impl EvolutionMode {
+    crate fn validate(&self) -> Result<(), ConfigError> {
+        match *self {
+            Self::Generational => Ok(()),
+
+            Self::SteadyState { max_age } => {
+                ensure(max_age > 0, "evolution_mode.max_age", "must be positive")
+            }
+        }
+    }
+}
+
+/// How parents are picked for the next generation
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Selection {
+    /// Each individual gets picked with probability proportional to its
+    /// fitness
+    RouletteWheel,
+
+    /// Picks `size` random individuals and returns the fittest one; the
+    /// larger the tournament, the larger the selection pressure
+    Tournament { size: usize },
+
+    /// Each individual gets picked with probability proportional to its
+    /// position on the fitness ranking, no matter how much it outperforms
+    /// the others - this prevents a single lucky bird from dominating the
+    /// population
+    Rank,
+}
+
+impl Default for Selection {
+    fn default() -> Self {
+        Self::RouletteWheel
+    }
+}
+
+impl Selection {
+    crate fn validate(&self) -> Result<(), ConfigError> {
+        match *self {
+            Self::Tournament { size } => ensure(
+                size > 0,
+                "selection.size",
+                "tournament must have at least one participant",
+            ),
+
+            Self::RouletteWheel | Self::Rank => Ok(()),
+        }
+    }
+}
+
+impl ga::SelectionMethod for Selection {
+    fn select<'a, I>(&self, rng: &mut dyn rand::RngCore, population: &'a [I]) -> &'a I
+    where
+        I: ga::Individual,
+    {
+        match self {
+            // When nobody has achieved anything yet (which happens quite
+            // often to predators, or at the beginning of a steady-state
+            // simulation), each individual is as good as any other - so
+            // we pick one at random, instead of letting the wheel panic
+            Self::RouletteWheel
+                if population
+                    .iter()
+                    .all(|individual| individual.fitness() <= 0.0) =>
+            {
+                population.choose(rng).expect("got an empty population")
+            }
+
+            Self::RouletteWheel => ga::RouletteWheelSelection::default().select(rng, population),
+
+            Self::Tournament { size } => (0..*size)
+                .map(|_| population.choose(rng).expect("got an empty population"))
+                .fold(None, |best: Option<&'a I>, individual| match best {
+                    Some(best) if best.fitness() >= individual.fitness() => Some(best),
+                    _ => Some(individual),
+                })
+                .expect("tournament should have at least one participant"),
+
+            Self::Rank => {
+                let mut ranking: Vec<_> = population.iter().collect();
+
+                ranking.sort_by(|a, b| {
+                    a.fitness()
+                        .partial_cmp(&b.fitness())
+                        .unwrap_or(std::cmp::Ordering::Equal)
+                });
+
+                // The weakest individual gets weight of 1, the next one 2,
+                // and so on, up to the fittest one with weight of `len`
+                let rank = ranking
+                    .iter()
+                    .enumerate()
+                    .collect::<Vec<_>>()
+                    .choose_weighted(rng, |(rank, _)| rank + 1)
+                    .expect("got an empty population")
+                    .0;
+
+                ranking[rank]
+            }
+        }
+    }
+}
+
+/// How two parents get combined into a child
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Crossover {
+    /// Each gene is taken from a random parent
+    Uniform,
+
+    /// Genes up to a random point are taken from the first parent, and
+    /// the rest from the second one
+    SinglePoint,
+
+    /// Ditto, but with `points` random points, switching between parents
+    /// at each one
+    MultiPoint { points: usize },
+}
+
+impl Default for Crossover {
+    fn default() -> Self {
+        Self::Uniform
+    }
+}
+
+impl Crossover {
+    crate fn validate(&self) -> Result<(), ConfigError> {
+        match *self {
+            Self::MultiPoint { points } => ensure(
+                points > 0,
+                "crossover.points",
+                "there must be at least one crossover point",
+            ),
+
+            Self::Uniform | Self::SinglePoint => Ok(()),
+        }
+    }
+}
+
+impl ga::CrossoverMethod for Crossover {
+    fn crossover(
+        &self,
+        rng: &mut dyn rand::RngCore,
+        parent_a: &ga::Chromosome,
+        parent_b: &ga::Chromosome,
+    ) -> ga::Chromosome {
+        assert_eq!(parent_a.len(), parent_b.len());
+
+        let points = match self {
+            Self::Uniform => {
+                return ga::UniformCrossover::default().crossover(rng, parent_a, parent_b);
+            }
+
+            Self::SinglePoint => 1,
+            Self::MultiPoint { points } => *points,
+        };
+
+        let mut points: Vec<_> = (0..points)
+            .map(|_| rng.gen_range(0..=parent_a.len()))
+            .collect();
+
+        points.sort_unstable();
+
+        parent_a
+            .iter()
+            .zip(parent_b.iter())
+            .enumerate()
+            .map(|(gene, (&a, &b))| {
+                // Number of points we've already passed determines which
+                // parent we're copying from at the moment
+                let passed = points.iter().filter(|&&point| point <= gene).count();
+
+                if passed % 2 == 0 {
+                    a
+                } else {
+                    b
+                }
+            })
+            .collect()
+    }
+}
+
+/// How children get mutated
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Mutation {
+    /// Each gene has `chance` to get nudged by up to `coeff`.
+    ///
+    /// Higher values can make the simulation more chaotic, which - a bit
+    /// counterintuitively - might allow for it to discover *better*
+    /// solutions; but the trade-off is that higher values might also
+    /// cause current, good enough solutions to be discarded.
+    Gaussian { chance: f32, coeff: f32 },
+
+    /// Same as `Gaussian`, but with `coeff` that adapts to how well the
+    /// evolution is going: each generation that fails to improve the
+    /// best fitness multiplies it by `factor` (shaking the population up),
+    /// while each generation that succeeds divides it by `factor` (letting
+    /// the population settle down).
+    ///
+    /// `coeff` always stays within `<min_coeff, max_coeff>`.
+    Adaptive {
+        chance: f32,
+        coeff: f32,
+        min_coeff: f32,
+        max_coeff: f32,
+        factor: f32,
+    },
+}
+
+impl Default for Mutation {
+    fn default() -> Self {
+        Self::Gaussian {
+            chance: 0.01,
+            coeff: 0.3,
+        }
+    }
+}
+
+impl Mutation {
+    crate fn validate(&self) -> Result<(), ConfigError> {
+        let (chance, coeff) = match *self {
+            Self::Gaussian { chance, coeff } => (chance, coeff),
+
+            Self::Adaptive {
+                chance,
+                coeff,
+                min_coeff,
+                max_coeff,
+                factor,
+            } => {
+                ensure(
+                    min_coeff >= 0.0 && min_coeff <= coeff && coeff <= max_coeff,
+                    "mutation.coeff",
+                    "must be within [min_coeff, max_coeff]",
+                )?;
+
+                ensure(
+                    factor.is_finite() && factor >= 1.0,
+                    "mutation.factor",
+                    "must be finite and at least 1.0",
+                )?;
+
+                (chance, coeff)
+            }
+        };
+
+        ensure(
+            (0.0..=1.0).contains(&chance),
+            "mutation.chance",
+            "must be within [0, 1]",
+        )?;
+
+        ensure(
+            coeff.is_finite() && coeff >= 0.0,
+            "mutation.coeff",
+            "must be finite and non-negative",
+        )
+    }
+
+    fn chance(&self) -> f32 {
+        match *self {
+            Self::Gaussian { chance, .. } | Self::Adaptive { chance, .. } => chance,
+        }
+    }
+
+    fn coeff(&self) -> f32 {
+        match *self {
+            Self::Gaussian { coeff, .. } | Self::Adaptive { coeff, .. } => coeff,
+        }
+    }
+}
+
+/// Part of the evolution that changes from one generation to another
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+crate struct EvolutionState {
+    generation: usize,
+    mutation_coeff: f32,
+    previous_best: Option<f32>,
+    best_ever: Option<f32>,
+}
+
+impl EvolutionState {
+    crate fn new(mutation: &Mutation) -> Self {
+        Self {
+            generation: 0,
+            mutation_coeff: mutation.coeff(),
+            previous_best: None,
+            best_ever: None,
+        }
+    }
+
+    /// Returns crossover & mutation operators; `structure` is present
+    /// only when brains' shape is evolvable (see: `NeuroevolutionConfig`)
+    fn operators(
+        &self,
+        crossover: &Crossover,
+        mutation: &Mutation,
+        structure: Option<&StructuralGenes>,
+    ) -> (StructuralCrossover, StructuralMutation) {
+        let crossover = StructuralCrossover {
+            crossover: crossover.clone(),
+            structure: structure.cloned(),
+        };
+
+        let mutation = StructuralMutation {
+            chance: mutation.chance(),
+            coeff: self.mutation_coeff,
+            structure: structure.cloned(),
+        };
+
+        (crossover, mutation)
+    }
+
+    /// Creates a single child out of two parents picked from the given
+    /// population; returns the child along with its parents' indices.
+    ///
+    /// (that's what `ga::GeneticAlgorithm::evolve()` does for an entire
+    /// population at once - doing it one bird at a time lets us know
+    /// who's whose parent.)
+    crate fn breed<I>(
+        &self,
+        selection: &Selection,
+        crossover: &Crossover,
+        mutation: &Mutation,
+        structure: Option<&StructuralGenes>,
+        rng: &mut dyn rand::RngCore,
+        population: &[I],
+    ) -> (ga::Chromosome, [usize; 2])
+    where
+        I: ga::Individual,
+    {
+        let (crossover, mutation) = self.operators(crossover, mutation, structure);
+
+        let parent_a = select_parent(selection, rng, population);
+        let parent_b = select_parent(selection, rng, population);
+
+        let mut child = crossover.crossover(
+            rng,
+            population[parent_a].chromosome(),
+            population[parent_b].chromosome(),
+        );
+
+        mutation.mutate(rng, &mut child);
+
+        (child, [parent_a, parent_b])
+    }
+
+    /// Number of generations evolved so far
+    crate fn generation(&self) -> usize {
+        self.generation
+    }
+
+    /// Current magnitude of mutations
+    crate fn mutation_coeff(&self) -> f32 {
+        self.mutation_coeff
+    }
+
+    /// Adjusts the state after a generation has been evolved, given
+    /// fitness of each of its birds.
+    crate fn update(&mut self, mutation: &Mutation, fitness: &[f32]) -> Statistics {
+        let mut stats = Statistics::new(self.generation, fitness);
+        let best = stats.max_fitness;
+
+        if let Mutation::Adaptive {
+            min_coeff,
+            max_coeff,
+            factor,
+            ..
+        } = *mutation
+        {
+            if let Some(previous_best) = self.previous_best {
+                let coeff = if best > previous_best {
+                    self.mutation_coeff / factor
+                } else {
+                    self.mutation_coeff * factor
+                };
+
+                self.mutation_coeff = coeff.clamp(min_coeff, max_coeff);
+            }
+        }
+
+        let best_ever = self.best_ever.map_or(best, |best_ever| best_ever.max(best));
+
+        stats.regressed = best < best_ever;
+        stats.best_fitness_ever = best_ever;
+
+        self.generation += 1;
+        self.previous_best = Some(best);
+        self.best_ever = Some(best_ever);
+
+        stats
+    }
+}
+
+/// Returns index of a parent picked with given selection method
+fn select_parent<I>(selection: &Selection, rng: &mut dyn rand::RngCore, population: &[I]) -> usize
+where
+    I: ga::Individual,
+{
+    let parent = selection.select(rng, population);
+
+    population
+        .iter()
+        .position(|individual| std::ptr::eq(individual, parent))
+        .expect("selected parent should come from the population")
+}
+
+/// Replaces the first `count` individuals of the `evolved` population
+/// with the `count` fittest individuals of the `current` one, so that
+/// they get to live another generation - unchanged.
+crate fn preserve_elites<I>(current: &[I], evolved: &mut [I], count: usize)
+where
+    I: ga::Individual + Clone,
+{
+    let mut ranking: Vec<_> = current.iter().collect();
+
+    ranking.sort_by(|a, b| {
+        b.fitness()
+            .partial_cmp(&a.fitness())
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+
+    for (slot, elite) in evolved.iter_mut().zip(ranking).take(count) {
+        *slot = elite.clone();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    #[derive(Clone)]
+    struct TestIndividual {
+        fitness: f32,
+        chromosome: ga::Chromosome,
+    }
+
+    impl TestIndividual {
+        fn new(fitness: f32) -> Self {
+            Self {
+                fitness,
+                chromosome: vec![fitness].into_iter().collect(),
+            }
+        }
+    }
+
+    impl ga::Individual for TestIndividual {
+        fn create(chromosome: ga::Chromosome) -> Self {
+            Self {
+                fitness: 0.0,
+                chromosome,
+            }
+        }
+
+        fn chromosome(&self) -> &ga::Chromosome {
+            &self.chromosome
+        }
+
+        fn fitness(&self) -> f32 {
+            self.fitness
+        }
+    }
+
+    /// Selects a thousand individuals and returns how many times each
+    /// one has been picked
+    fn histogram(selection: Selection) -> Vec<usize> {
+        let mut rng = ChaCha8Rng::from_seed(Default::default());
+
+        let population: Vec<_> = [2.0, 1.0, 100.0, 3.0]
+            .iter()
+            .map(|&fitness| TestIndividual::new(fitness))
+            .collect();
+
+        let mut histogram = vec![0; population.len()];
+
+        for _ in 0..1000 {
+            let fitness = selection.select(&mut rng, &population).fitness;
+
+            let id = population
+                .iter()
+                .position(|individual| individual.fitness == fitness)
+                .unwrap();
+
+            histogram[id] += 1;
+        }
+
+        histogram
+    }
+
+    #[test]
+    fn tournament_selection() {
+        let histogram = histogram(Selection::Tournament { size: 2 });
+
+        // The weakest individual can win only against itself
+        assert!(histogram[1] < 100);
+        assert!(histogram[2] > 300);
+    }
+
+    #[test]
+    fn tournament_selection_of_one_is_random() {
+        let histogram = histogram(Selection::Tournament { size: 1 });
+
+        assert!(histogram.iter().all(|&count| count > 200 && count < 300));
+    }
+
+    #[test]
+    fn rank_selection() {
+        let histogram = histogram(Selection::Rank);
+
+        // Ranks are 2, 1, 4, 3 - so, compared to the roulette wheel, the
+        // fittest individual doesn't get to dominate everyone else
+        assert!(histogram[1] < histogram[0]);
+        assert!(histogram[0] < histogram[3]);
+        assert!(histogram[3] < histogram[2]);
+        assert!(histogram[2] < 500);
+    }
+
+    #[test]
+    fn roulette_wheel_selection_of_nobodies_is_random() {
+        let mut rng = ChaCha8Rng::from_seed(Default::default());
+        let population: Vec<_> = (0..4).map(|_| TestIndividual::new(0.0)).collect();
+        let mut histogram = vec![0; population.len()];
+
+        for _ in 0..1000 {
+            let parent = Selection::RouletteWheel.select(&mut rng, &population);
+
+            let id = population
+                .iter()
+                .position(|individual| std::ptr::eq(individual, parent))
+                .unwrap();
+
+            histogram[id] += 1;
+        }
+
+        assert!(histogram.iter().all(|&count| count > 200 && count < 300));
+    }
+
+    fn crossover(crossover: Crossover) -> Vec<f32> {
+        let mut rng = ChaCha8Rng::from_seed(Default::default());
+        let parent_a: ga::Chromosome = (0..20).map(|_| 1.0).collect();
+        let parent_b: ga::Chromosome = (0..20).map(|_| 2.0).collect();
+
+        crossover
+            .crossover(&mut rng, &parent_a, &parent_b)
+            .into_iter()
+            .collect()
+    }
+
+    /// Returns in how many places the child switches from one parent to
+    /// another
+    fn switches(child: &[f32]) -> usize {
+        child
+            .windows(2)
+            .filter(|genes| genes[0] != genes[1])
+            .count()
+    }
+
+    #[test]
+    fn single_point_crossover() {
+        let child = crossover(Crossover::SinglePoint);
+
+        assert_eq!(child.len(), 20);
+        assert!(switches(&child) <= 1);
+        assert!(child[0] == 1.0 || switches(&child) == 0);
+    }
+
+    #[test]
+    fn multi_point_crossover() {
+        let child = crossover(Crossover::MultiPoint { points: 4 });
+
+        assert_eq!(child.len(), 20);
+        assert!(switches(&child) <= 4);
+        assert!(child.contains(&1.0) && child.contains(&2.0));
+    }
+
+    #[test]
+    fn adaptive_mutation() {
+        let mutation = Mutation::Adaptive {
+            chance: 0.01,
+            coeff: 0.2,
+            min_coeff: 0.1,
+            max_coeff: 0.3,
+            factor: 2.0,
+        };
+
+        let mut state = EvolutionState::new(&mutation);
+
+        let evolve = |state: &mut EvolutionState, best_fitness: f32| {
+            state.update(&mutation, &[0.5, best_fitness]);
+            state.mutation_coeff()
+        };
+
+        // First generation has nothing to compare to
+        assert_eq!(evolve(&mut state, 1.0), 0.2);
+
+        // Stagnation shakes things up...
+        assert_eq!(evolve(&mut state, 1.0), 0.3);
+
+        // ... and progress calms them down
+        assert_eq!(evolve(&mut state, 2.0), 0.15);
+        assert_eq!(evolve(&mut state, 3.0), 0.1);
+    }
+
+    #[test]
+    fn regressions() {
+        let mutation = Mutation::default();
+        let mut state = EvolutionState::new(&mutation);
+
+        let mut evolve = |best_fitness: f32| {
+            let stats = state.update(&mutation, &[0.5, best_fitness]);
+
+            (
+                stats.generation(),
+                stats.best_fitness_ever(),
+                stats.regressed(),
+            )
+        };
+
+        assert_eq!(evolve(2.0), (0, 2.0, false));
+        assert_eq!(evolve(3.0), (1, 3.0, false));
+        assert_eq!(evolve(1.0), (2, 3.0, true));
+        assert_eq!(evolve(3.0), (3, 3.0, false));
+    }
+
+    #[test]
+    fn elitism() {
+        let current: Vec<_> = [2.0, 5.0, 1.0, 4.0]
+            .iter()
+            .map(|&fitness| TestIndividual::new(fitness))
+            .collect();
+
+        let mut evolved: Vec<_> = (0..4).map(|_| TestIndividual::new(0.0)).collect();
+
+        preserve_elites(&current, &mut evolved, 2);
+
+        let genes: Vec<_> = evolved
+            .iter()
+            .map(|individual| individual.chromosome[0])
+            .collect();
+
+        assert_eq!(genes, vec![5.0, 4.0, 0.0, 0.0]);
+    }
+}
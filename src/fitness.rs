@@ -0,0 +1,201 @@
+/// What a bird has achieved during its life; that's what fitness
+/// functions base their judgement on.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LifetimeStats {
+    crate food_eaten: usize,
+    crate nutrition: f32,
+    crate distance_travelled: f32,
+    crate time_to_first_food: Option<usize>,
+    crate energy_spent: f32,
+    crate turning_effort: f32,
+    crate lifetime: usize,
+}
+
+impl LifetimeStats {
+    /// Number of foods eaten
+    pub fn food_eaten(&self) -> usize {
+        self.food_eaten
+    }
+
+    /// Total nutritional value of the foods eaten; unless food has
+    /// varying nutrition (see: `FoodConfig::nutrition`), it's the same as
+    /// the number of foods eaten
+    pub fn nutrition(&self) -> f32 {
+        self.nutrition
+    }
+
+    /// Total length of the path flown
+    pub fn distance_travelled(&self) -> f32 {
+        self.distance_travelled
+    }
+
+    /// Number of steps it took the bird to find its first food (or `None`
+    /// if it hasn't found any)
+    pub fn time_to_first_food(&self) -> Option<usize> {
+        self.time_to_first_food
+    }
+
+    /// Effort put into changing speed - i.e. sum of all accelerations
+    /// and decelerations
+    pub fn energy_spent(&self) -> f32 {
+        self.energy_spent
+    }
+
+    /// Effort put into changing direction - i.e. sum of all rotations,
+    /// in radians
+    pub fn turning_effort(&self) -> f32 {
+        self.turning_effort
+    }
+
+    /// Number of steps lived
+    pub fn lifetime(&self) -> usize {
+        self.lifetime
+    }
+}
+
+/// Decides how good a bird is - the better it is, the bigger chance it
+/// gets to pass its genes to the next generation.
+///
+/// Since our genetic algorithm relies on roulette wheel selection,
+/// negative fitness doesn't make sense - such values are treated as
+/// zero.
+pub trait FitnessFunction {
+    fn fitness(&self, stats: &LifetimeStats) -> f32;
+}
+
+/// Fitness functions that come out of the box
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Fitness {
+    /// Number of foods eaten - the more, the better
+    Satiation,
+
+    /// Number of foods eaten per distance travelled - rewards birds
+    /// that fly straight to the food, instead of circling around
+    Efficiency,
+
+    WeightedSum(WeightedSum),
+}
+
+impl Default for Fitness {
+    fn default() -> Self {
+        Self::Satiation
+    }
+}
+
+impl FitnessFunction for Fitness {
+    fn fitness(&self, stats: &LifetimeStats) -> f32 {
+        match self {
+            Self::Satiation => stats.food_eaten as f32,
+
+            Self::Efficiency => {
+                if stats.distance_travelled > 0.0 {
+                    stats.food_eaten as f32 / stats.distance_travelled
+                } else {
+                    0.0
+                }
+            }
+
+            Self::WeightedSum(sum) => sum.fitness(stats),
+        }
+    }
+}
+
+/// Multi-objective fitness: a weighted sum of the lifetime statistics.
+///
+/// Weights can be negative, to penalize given trait - e.g. setting
+/// `turning_effort` to `-0.1` will favour birds that don't spin around
+/// like crazy.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct WeightedSum {
+    pub food_eaten: f32,
+    pub nutrition: f32,
+    pub distance_travelled: f32,
+
+    /// Weight of the "how early the first food has been found" score,
+    /// which ranges from 1.0 (right at the beginning of bird's life) to
+    /// 0.0 (never)
+    pub early_food: f32,
+
+    pub energy_spent: f32,
+    pub turning_effort: f32,
+}
+
+impl FitnessFunction for WeightedSum {
+    fn fitness(&self, stats: &LifetimeStats) -> f32 {
+        let early_food = match stats.time_to_first_food {
+            Some(time) => 1.0 - (time as f32) / (stats.lifetime.max(1) as f32),
+            None => 0.0,
+        };
+
+        self.food_eaten * (stats.food_eaten as f32)
+            + self.nutrition * stats.nutrition
+            + self.distance_travelled * stats.distance_travelled
+            + self.early_food * early_food
+            + self.energy_spent * stats.energy_spent
+            + self.turning_effort * stats.turning_effort
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    fn stats() -> LifetimeStats {
+        LifetimeStats {
+            food_eaten: 6,
+            nutrition: 4.5,
+            distance_travelled: 4.0,
+            time_to_first_food: Some(25),
+            energy_spent: 0.5,
+            turning_effort: 10.0,
+            lifetime: 100,
+        }
+    }
+
+    #[test]
+    fn satiation() {
+        assert_relative_eq!(Fitness::Satiation.fitness(&stats()), 6.0);
+    }
+
+    #[test]
+    fn efficiency() {
+        assert_relative_eq!(Fitness::Efficiency.fitness(&stats()), 1.5);
+
+        assert_relative_eq!(Fitness::Efficiency.fitness(&LifetimeStats::default()), 0.0);
+    }
+
+    #[test]
+    fn weighted_sum() {
+        let fitness = Fitness::WeightedSum(WeightedSum {
+            food_eaten: 1.0,
+            nutrition: 0.0,
+            distance_travelled: 0.5,
+            early_food: 4.0,
+            energy_spent: -2.0,
+            turning_effort: -0.1,
+        });
+
+        // 6.0 + 2.0 + 3.0 - 1.0 - 1.0
+        assert_relative_eq!(fitness.fitness(&stats()), 9.0);
+    }
+
+    #[test]
+    fn weighted_nutrition() {
+        let fitness = Fitness::WeightedSum(WeightedSum {
+            food_eaten: 0.0,
+            nutrition: 2.0,
+            distance_travelled: 0.0,
+            early_food: 0.0,
+            energy_spent: 0.0,
+            turning_effort: 0.0,
+        });
+
+        // 4.5 * 2.0
+        assert_relative_eq!(fitness.fitness(&stats()), 9.0);
+    }
+}
@@ -1,19 +1,376 @@
 use nalgebra as na;
+use rand::seq::SliceRandom;
 use rand::Rng;
+use std::f32::consts::PI;
+use std::ops::RangeInclusive;
+
+use crate::config::ensure;
+use crate::{ConfigError, FoodId};
 
 #[derive(Debug)]
 pub struct Food {
+    crate id: FoodId,
     crate position: na::Point2<f32>,
+    crate nutrition: f32,
+
+    /// Number of steps left until this food regrows (see:
+    /// `FoodConfig::regrowth_delay`)
+    crate regrowth: usize,
+
+    /// Whether this food is out of season (see: `FoodConfig::seasons`)
+    crate dormant: bool,
 }
 
 impl Food {
     pub fn random(rng: &mut dyn rand::RngCore) -> Self {
+        Self::at(rng.gen())
+    }
+
+    crate fn at(position: na::Point2<f32>) -> Self {
         Self {
-            position: rng.gen(),
+            id: FoodId::default(),
+            position,
+            nutrition: 1.0,
+            regrowth: 0,
+            dormant: false,
         }
     }
 
+    pub fn id(&self) -> FoodId {
+        self.id
+    }
+
     pub fn position(&self) -> na::Point2<f32> {
         self.position
     }
+
+    /// How nourishing this food is, relative to `EnergyConfig::food_value`
+    pub fn nutrition(&self) -> f32 {
+        self.nutrition
+    }
+
+    /// Whether this food can be seen and eaten - it cannot while it's
+    /// regrowing or out of season
+    pub fn is_available(&self) -> bool {
+        self.regrowth == 0 && !self.dormant
+    }
+}
+
+/// Food dynamics; when disabled (which is the default), eaten food
+/// immediately reappears at a random place, so the world always
+/// contains the same amount of it, uniformly scattered.
+///
+/// Making food scarce, clustered or seasonal gives birds a reason to
+/// forage in different ways - e.g. to stick around a patch instead of
+/// wandering aimlessly.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct FoodConfig {
+    /// Number of steps it takes an eaten food to regrow (at its new
+    /// place); until then, nobody can see nor eat it
+    pub regrowth_delay: usize,
+
+    /// Where food grows
+    pub distribution: FoodDistribution,
+
+    /// Seasonal abundance cycles; when `None`, it's always summer
+    pub seasons: Option<Seasons>,
+
+    /// Range of nutritional values, relative to `EnergyConfig::food_value`;
+    /// each food gets a random one each time it grows.
+    ///
+    /// Note that nutrition affects only the energy model (see:
+    /// `SimulationConfig::energy`) and `LifetimeStats::nutrition()` -
+    /// fitness functions based on the number of foods eaten don't care.
+    pub nutrition: RangeInclusive<f32>,
+}
+
+impl FoodConfig {
+    crate fn validate(&self) -> Result<(), ConfigError> {
+        self.distribution.validate()?;
+
+        if let Some(seasons) = &self.seasons {
+            seasons.validate()?;
+        }
+
+        ensure(
+            self.nutrition.start().is_finite()
+                && self.nutrition.end().is_finite()
+                && *self.nutrition.start() >= 0.0
+                && self.nutrition.start() <= self.nutrition.end(),
+            "food.nutrition",
+            "must be a finite, non-empty range of non-negative values",
+        )
+    }
+
+    /// Returns patches the food is going to grow in
+    crate fn patches(&self, rng: &mut dyn rand::RngCore) -> Vec<FoodPatch> {
+        match self.distribution {
+            FoodDistribution::Uniform => Vec::new(),
+
+            FoodDistribution::Patches { count, .. } => (0..count)
+                .map(|_| FoodPatch {
+                    center: rng.gen(),
+                    heading: rng.gen_range(0.0..(2.0 * PI)),
+                })
+                .collect(),
+        }
+    }
+
+    /// Returns where a new food should grow, if it's supposed to grow
+    /// inside one of the patches; otherwise it's up to
+    /// `SimulationConfig::food_placement`.
+    crate fn patch_position(
+        &self,
+        patches: &[FoodPatch],
+        rng: &mut dyn rand::RngCore,
+    ) -> Option<na::Point2<f32>> {
+        match self.distribution {
+            FoodDistribution::Uniform => None,
+
+            FoodDistribution::Patches { radius, .. } => {
+                let patch = patches.choose(rng).expect("there should be some patches");
+                let offset = gaussian(rng) * radius;
+
+                // Food that lands outside the map wraps around, so that
+                // patches lying near the edges don't get thinner
+                Some(na::Point2::new(
+                    (patch.center.x + offset.x).rem_euclid(1.0),
+                    (patch.center.y + offset.y).rem_euclid(1.0),
+                ))
+            }
+        }
+    }
+
+    /// Grows a new food at given position
+    crate fn grow(&self, position: na::Point2<f32>, rng: &mut dyn rand::RngCore) -> Food {
+        Food {
+            nutrition: rng.gen_range(self.nutrition.clone()),
+            ..Food::at(position)
+        }
+    }
+
+    /// Moves patches around a bit
+    crate fn drift(&self, patches: &mut [FoodPatch], rng: &mut dyn rand::RngCore) {
+        let drift = match self.distribution {
+            FoodDistribution::Patches { drift, .. } if drift > 0.0 => drift,
+            _ => return,
+        };
+
+        for patch in patches {
+            patch.heading += rng.gen_range(-PATCH_WANDER..=PATCH_WANDER);
+
+            patch.center.x = (patch.center.x + drift * patch.heading.cos()).rem_euclid(1.0);
+            patch.center.y = (patch.center.y + drift * patch.heading.sin()).rem_euclid(1.0);
+        }
+    }
+
+    /// Returns which fraction of the foods is in season at given moment
+    crate fn abundance(&self, clock: usize) -> f32 {
+        self.seasons
+            .as_ref()
+            .map_or(1.0, |seasons| seasons.abundance(clock))
+    }
+}
+
+impl Default for FoodConfig {
+    fn default() -> Self {
+        Self {
+            regrowth_delay: 0,
+            distribution: FoodDistribution::default(),
+            seasons: None,
+            nutrition: 1.0..=1.0,
+        }
+    }
+}
+
+/// Where food grows
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FoodDistribution {
+    /// Anywhere - as laid out by `SimulationConfig::food_placement`
+    Uniform,
+
+    /// Inside `count` patches; food is scattered around patch's center
+    /// with normal distribution, `radius` being its standard deviation.
+    ///
+    /// Each step, each patch moves by `drift` into a (slowly changing)
+    /// direction - so birds can't just memorize where the food is.
+    Patches {
+        count: usize,
+        radius: f32,
+        drift: f32,
+    },
+}
+
+impl Default for FoodDistribution {
+    fn default() -> Self {
+        Self::Uniform
+    }
+}
+
+impl FoodDistribution {
+    fn validate(&self) -> Result<(), ConfigError> {
+        match *self {
+            Self::Uniform => Ok(()),
+
+            Self::Patches {
+                count,
+                radius,
+                drift,
+            } => {
+                ensure(count > 0, "food.distribution.count", "must be positive")?;
+
+                ensure(
+                    radius.is_finite() && radius > 0.0,
+                    "food.distribution.radius",
+                    "must be finite and positive",
+                )?;
+
+                ensure(
+                    drift.is_finite() && drift >= 0.0,
+                    "food.distribution.drift",
+                    "must be finite and non-negative",
+                )
+            }
+        }
+    }
+}
+
+/// Seasonal abundance cycles: as the winter comes, foods go dormant one
+/// by one - up to `scarcity` of them at the very peak of the winter -
+/// and then slowly get back as the summer comes.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Seasons {
+    /// Number of steps a whole year (summer-winter-summer) takes
+    pub length: usize,
+
+    /// Fraction of foods that go dormant at the peak of the winter
+    pub scarcity: f32,
+}
+
+impl Seasons {
+    fn validate(&self) -> Result<(), ConfigError> {
+        ensure(self.length > 0, "food.seasons.length", "must be positive")?;
+
+        ensure(
+            (0.0..=1.0).contains(&self.scarcity),
+            "food.seasons.scarcity",
+            "must be within [0, 1]",
+        )
+    }
+
+    fn abundance(&self, clock: usize) -> f32 {
+        let phase = (clock % self.length) as f32 / self.length as f32;
+        let winter = (1.0 - (2.0 * PI * phase).cos()) / 2.0;
+
+        1.0 - self.scarcity * winter
+    }
+}
+
+/// Place where food grows (see: `FoodDistribution::Patches`)
+#[derive(Clone, Debug, PartialEq)]
+crate struct FoodPatch {
+    crate center: na::Point2<f32>,
+    crate heading: f32,
+}
+
+/// How much patches can change their direction during a single step
+const PATCH_WANDER: f32 = 0.1;
+
+/// Returns a random vector with standard normal distribution (see:
+/// Box-Muller transform)
+fn gaussian(rng: &mut dyn rand::RngCore) -> na::Vector2<f32> {
+    // `1.0 - ...` makes it lie within (0, 1], which keeps the logarithm
+    // finite
+    let u1 = 1.0 - rng.gen::<f32>();
+    let u2 = rng.gen::<f32>();
+
+    let r = (-2.0 * u1.ln()).sqrt();
+    let angle = 2.0 * PI * u2;
+
+    na::Vector2::new(r * angle.cos(), r * angle.sin())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Simulation, SimulationConfig, WorldTopology};
+    use approx::assert_relative_eq;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+    use test_case::test_case;
+
+    #[test_case(0, 1.0)]
+    #[test_case(25, 0.8)]
+    #[test_case(50, 0.6)]
+    #[test_case(75, 0.8)]
+    #[test_case(100, 1.0)]
+    fn seasons(clock: usize, expected: f32) {
+        let seasons = Seasons {
+            length: 100,
+            scarcity: 0.4,
+        };
+
+        assert_relative_eq!(seasons.abundance(clock), expected, epsilon = 1e-6);
+    }
+
+    #[test]
+    fn eaten_food_regrows_after_delay() {
+        let mut rng = ChaCha8Rng::from_seed(Default::default());
+
+        // Our bird reaches the entire map, so it eats the food as soon
+        // as it regrows
+        let config = SimulationConfig {
+            animals: 1,
+            foods: 1,
+            eat_range: 2.0,
+            food: Some(FoodConfig {
+                regrowth_delay: 10,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let mut sim = Simulation::from_config(config, &mut rng).unwrap();
+
+        for _ in 0..25 {
+            sim.step(&mut rng);
+        }
+
+        assert_eq!(sim.world().animals()[0].stats().food_eaten(), 3);
+    }
+
+    #[test]
+    fn patches_cluster_food() {
+        let mut rng = ChaCha8Rng::from_seed(Default::default());
+
+        let config = SimulationConfig {
+            foods: 100,
+            food: Some(FoodConfig {
+                distribution: FoodDistribution::Patches {
+                    count: 1,
+                    radius: 0.01,
+                    drift: 0.0,
+                },
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let sim = Simulation::from_config(config, &mut rng).unwrap();
+        let foods = sim.world().foods();
+
+        for food in foods {
+            let distance = WorldTopology::Torus.distance(food.position(), foods[0].position());
+
+            assert!(
+                distance < 0.1,
+                "food lies {} away from the others",
+                distance
+            );
+        }
+    }
 }
@@ -0,0 +1,452 @@
+use std::{error, fmt};
+
+use crate::animal::Brain;
+use crate::{ConfigError, VisionChannel};
+
+/// Brain of a single bird, detached from the bird itself.
+///
+/// Genomes can be pulled out of a running simulation (e.g. to keep the
+/// best bird around) and put back into another one - as long as that
+/// other simulation uses eyes of the same shape.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Genome {
+    /// Number of eye cells this brain has been evolved for; together
+    /// with `channels`, determines the shape of the entire neural network
+    /// (see: `.layers()`)
+    eye_cells: usize,
+
+    /// Eye channels this brain has been evolved for (see:
+    /// `SimulationConfig::vision`)
+    #[cfg_attr(feature = "serde", serde(default = "Genome::default_channels"))]
+    channels: Vec<VisionChannel>,
+
+    /// Number of hidden neurons, if it differs from the default one (see:
+    /// `SimulationConfig::neuroevolution`)
+    #[cfg_attr(feature = "serde", serde(default))]
+    hidden: Option<usize>,
+
+    genes: Vec<f32>,
+
+    /// Genes describing the body, if it's evolvable (see:
+    /// `SimulationConfig::body`)
+    #[cfg_attr(feature = "serde", serde(default))]
+    body: Vec<f32>,
+}
+
+impl Genome {
+    /// Creates a genome for a brain that sees nothing but food
+    pub fn new(eye_cells: usize, genes: Vec<f32>) -> Result<Self, GenomeError> {
+        Self::with_channels(eye_cells, Self::default_channels(), genes)
+    }
+
+    pub fn with_channels(
+        eye_cells: usize,
+        channels: Vec<VisionChannel>,
+        genes: Vec<f32>,
+    ) -> Result<Self, GenomeError> {
+        let hidden = Brain::default_hidden(eye_cells * channels.len());
+
+        Self::with_hidden(eye_cells, channels, hidden, genes)
+    }
+
+    /// Creates a genome for a brain with given number of hidden neurons
+    /// (see: `SimulationConfig::neuroevolution`)
+    pub fn with_hidden(
+        eye_cells: usize,
+        channels: Vec<VisionChannel>,
+        hidden: usize,
+        genes: Vec<f32>,
+    ) -> Result<Self, GenomeError> {
+        // Default number of hidden neurons doesn't have to be spelled
+        // out, which keeps the genomes exported from "regular"
+        // simulations the same as they've always been
+        let hidden = if hidden == Brain::default_hidden(eye_cells * channels.len()) {
+            None
+        } else {
+            Some(hidden)
+        };
+
+        let this = Self {
+            eye_cells,
+            channels,
+            hidden,
+            genes,
+            body: Vec::new(),
+        };
+
+        this.validate()?;
+
+        Ok(this)
+    }
+
+    pub fn eye_cells(&self) -> usize {
+        self.eye_cells
+    }
+
+    pub fn channels(&self) -> &[VisionChannel] {
+        &self.channels
+    }
+
+    /// Number of neurons in the network's hidden layer
+    pub fn hidden(&self) -> usize {
+        self.hidden
+            .unwrap_or_else(|| Brain::default_hidden(self.inputs()))
+    }
+
+    /// Returns number of neurons in each layer of the neural network
+    /// described by this genome, starting from the input layer.
+    pub fn layers(&self) -> Vec<usize> {
+        Brain::layers(self.inputs(), self.hidden()).to_vec()
+    }
+
+    /// Returns the network's weights (and biases), in the order used by
+    /// `lib_neural_network::Network::from_weights()`.
+    pub fn genes(&self) -> &[f32] {
+        &self.genes
+    }
+
+    /// Attaches body genes to this genome (see: `SimulationConfig::body`)
+    pub fn with_body(mut self, body: Vec<f32>) -> Self {
+        self.body = body;
+        self
+    }
+
+    pub fn body(&self) -> &[f32] {
+        &self.body
+    }
+
+    crate fn into_genes(self) -> Vec<f32> {
+        self.genes
+    }
+
+    /// Checks whether the genes match the network's topology.
+    ///
+    /// (genomes that went through e.g. serde could've been tampered with,
+    /// so it's not enough to check this only inside `::new()`.)
+    crate fn validate(&self) -> Result<(), GenomeError> {
+        if self.eye_cells == 0 || self.hidden() == 0 {
+            return Err(GenomeError::UnsupportedShape {
+                eye_cells: self.eye_cells,
+                hidden: self.hidden(),
+            });
+        }
+
+        let expected = Brain::chromosome_len_for(self.inputs(), self.hidden());
+
+        if self.genes.len() == expected {
+            Ok(())
+        } else {
+            Err(GenomeError::LengthMismatch {
+                expected,
+                actual: self.genes.len(),
+            })
+        }
+    }
+
+    fn inputs(&self) -> usize {
+        self.eye_cells * self.channels.len()
+    }
+
+    fn default_channels() -> Vec<VisionChannel> {
+        vec![VisionChannel::Food]
+    }
+}
+
+/// Describes why given `Genome` cannot be used
+#[derive(Clone, Debug, PartialEq)]
+pub enum GenomeError {
+    /// Number of genes doesn't match the network's topology
+    LengthMismatch { expected: usize, actual: usize },
+
+    /// Genome has been evolved for an eye of a different shape than the
+    /// one used in the simulation
+    EyeMismatch { expected: usize, actual: usize },
+
+    /// Genome has been evolved for an eye seeing through different
+    /// channels than the one used in the simulation - e.g. for a bird
+    /// that's able to see predators
+    ChannelMismatch {
+        expected: Vec<VisionChannel>,
+        actual: Vec<VisionChannel>,
+    },
+
+    /// Number of body genes doesn't match the traits evolvable in the
+    /// simulation
+    BodyMismatch { expected: usize, actual: usize },
+
+    /// Brain's shape doesn't fit the simulation - e.g. it's got more
+    /// hidden neurons than `NeuroevolutionConfig::max_hidden`
+    UnsupportedShape { eye_cells: usize, hidden: usize },
+}
+
+impl fmt::Display for GenomeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::LengthMismatch { expected, actual } => write!(
+                f,
+                "genome has {} genes, but its topology requires {}",
+                actual, expected
+            ),
+
+            Self::EyeMismatch { expected, actual } => write!(
+                f,
+                "genome has been evolved for {} eye cells, but the simulation uses {}",
+                actual, expected
+            ),
+
+            Self::ChannelMismatch { expected, actual } => write!(
+                f,
+                "genome has been evolved for eye channels {:?}, but the simulation uses {:?}",
+                actual, expected
+            ),
+
+            Self::BodyMismatch { expected, actual } => write!(
+                f,
+                "genome has {} body genes, but the simulation requires {}",
+                actual, expected
+            ),
+
+            Self::UnsupportedShape { eye_cells, hidden } => write!(
+                f,
+                "genome's brain ({} eye cells, {} hidden neurons) doesn't fit the simulation",
+                eye_cells, hidden
+            ),
+        }
+    }
+}
+
+impl error::Error for GenomeError {
+    //
+}
+
+/// Describes why a simulation couldn't be seeded with given population
+#[derive(Clone, Debug, PartialEq)]
+pub enum PopulationError {
+    InvalidConfig(ConfigError),
+
+    /// There are more genomes than birds in the world
+    TooManyGenomes {
+        max: usize,
+        actual: usize,
+    },
+
+    /// One of the genomes cannot be used
+    InvalidGenome {
+        index: usize,
+        error: GenomeError,
+    },
+}
+
+impl fmt::Display for PopulationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidConfig(err) => write!(f, "{}", err),
+
+            Self::TooManyGenomes { max, actual } => write!(
+                f,
+                "got {} genomes, but the world can hold at most {} birds",
+                actual, max
+            ),
+
+            Self::InvalidGenome { index, error } => write!(f, "genome #{}: {}", index, error),
+        }
+    }
+}
+
+impl error::Error for PopulationError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::InvalidConfig(err) => Some(err),
+            Self::TooManyGenomes { .. } => None,
+            Self::InvalidGenome { error, .. } => Some(error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Animal, Collision, Obstacle, ObstacleConfig, Simulation, SimulationConfig};
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    #[test]
+    fn layers() {
+        let genome = Genome::new(3, vec![0.0; 4 * 6 + 7 * 2]).unwrap();
+
+        assert_eq!(genome.layers(), vec![3, 6, 2]);
+    }
+
+    #[test]
+    fn layers_with_channels() {
+        let genome = Genome::with_channels(
+            3,
+            vec![VisionChannel::Food, VisionChannel::Birds],
+            vec![0.0; 7 * 12 + 13 * 2],
+        )
+        .unwrap();
+
+        assert_eq!(genome.layers(), vec![6, 12, 2]);
+    }
+
+    #[test]
+    fn rejects_genes_not_matching_topology() {
+        assert_eq!(
+            Genome::new(3, vec![0.0; 10]),
+            Err(GenomeError::LengthMismatch {
+                expected: 38,
+                actual: 10
+            }),
+        );
+    }
+
+    #[test]
+    fn rejects_genome_for_different_eye() {
+        let mut rng = ChaCha8Rng::from_seed(Default::default());
+        let config = SimulationConfig::default();
+        let genome = Genome::new(3, vec![0.0; 38]).unwrap();
+
+        assert_eq!(
+            Animal::from_genome(genome, &config, &mut rng).err(),
+            Some(GenomeError::EyeMismatch {
+                expected: config.eye_cells,
+                actual: 3
+            }),
+        );
+    }
+
+    #[test]
+    fn rejects_genome_without_body() {
+        let mut rng = ChaCha8Rng::from_seed(Default::default());
+        let genome = Simulation::random(&mut rng).genomes().remove(0);
+
+        let config = SimulationConfig {
+            body: Some(Default::default()),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            Animal::from_genome(genome, &config, &mut rng).err(),
+            Some(GenomeError::BodyMismatch {
+                expected: 2,
+                actual: 0
+            }),
+        );
+    }
+
+    #[test]
+    fn survives_export_and_import() {
+        let mut rng = ChaCha8Rng::from_seed(Default::default());
+        let mut sim = Simulation::random(&mut rng);
+
+        let champion = sim.genomes().remove(3);
+
+        sim.import_genome(champion.clone(), &mut rng).unwrap();
+
+        let occurrences = sim
+            .genomes()
+            .into_iter()
+            .filter(|genome| *genome == champion)
+            .count();
+
+        assert_eq!(occurrences, 2);
+    }
+
+    #[test]
+    fn imported_birds_avoid_obstacles() {
+        let mut rng = ChaCha8Rng::from_seed(Default::default());
+
+        let config = SimulationConfig {
+            obstacles: Some(ObstacleConfig {
+                shapes: vec![Obstacle::Rect {
+                    min: [0.1, 0.1],
+                    max: [0.9, 0.9],
+                }],
+                collision: Collision::Slide,
+            }),
+            ..Default::default()
+        };
+
+        let mut sim = Simulation::from_config(config, &mut rng).unwrap();
+        let champion = sim.genomes().remove(3);
+
+        for _ in 0..20 {
+            sim.import_genome(champion.clone(), &mut rng).unwrap();
+
+            let newcomer = sim
+                .world()
+                .animals()
+                .iter()
+                .max_by_key(|animal| animal.id())
+                .unwrap();
+
+            let [x, y] = [newcomer.position().x, newcomer.position().y];
+
+            assert!(
+                !(0.11..0.89).contains(&x) || !(0.11..0.89).contains(&y),
+                "bird got imported into the rectangle: {}, {}",
+                x,
+                y
+            );
+        }
+    }
+
+    mod population {
+        use super::*;
+
+        fn config() -> SimulationConfig {
+            SimulationConfig {
+                animals: 5,
+                ..Default::default()
+            }
+        }
+
+        #[test]
+        fn mixes_given_and_random_birds() {
+            let mut rng = ChaCha8Rng::from_seed(Default::default());
+            let elite = Simulation::from_config(config(), &mut rng)
+                .unwrap()
+                .genomes();
+
+            let sim = Simulation::with_population(elite[..2].to_vec(), config(), &mut rng).unwrap();
+
+            let genomes = sim.genomes();
+
+            assert_eq!(genomes.len(), 5);
+            assert_eq!(genomes[..2], elite[..2]);
+            assert!(!elite.contains(&genomes[2]));
+        }
+
+        #[test]
+        fn rejects_too_many_genomes() {
+            let mut rng = ChaCha8Rng::from_seed(Default::default());
+            let elite = Simulation::from_config(config(), &mut rng)
+                .unwrap()
+                .genomes();
+
+            let mut genomes = elite.clone();
+            genomes.extend(elite);
+
+            assert_eq!(
+                Simulation::with_population(genomes, config(), &mut rng).err(),
+                Some(PopulationError::TooManyGenomes { max: 5, actual: 10 }),
+            );
+        }
+
+        #[test]
+        fn rejects_invalid_genome() {
+            let mut rng = ChaCha8Rng::from_seed(Default::default());
+            let mut genomes = Simulation::from_config(config(), &mut rng)
+                .unwrap()
+                .genomes();
+
+            genomes[1] = Genome::new(3, vec![0.0; 38]).unwrap();
+
+            assert!(matches!(
+                Simulation::with_population(genomes, config(), &mut rng),
+                Err(PopulationError::InvalidGenome { index: 1, .. }),
+            ));
+        }
+    }
+}
@@ -0,0 +1,255 @@
+use nalgebra as na;
+
+use crate::WorldTopology;
+
+/// Upper bound for the number of columns (and rows), so that a world
+/// with a gazillion foods doesn't make us allocate a gazillion cells.
+const MAX_COLS: usize = 256;
+
+/// Uniform grid that allows to quickly find items (foods, mostly) lying
+/// near given point.
+///
+/// Instead of checking each bird against each food, we put foods into
+/// buckets:
+///
+/// ---------------------
+/// |   %|    |    |    |
+/// |    | %  |    |    |
+/// ---------------------
+/// |    |  @ |    |  % |
+/// |    | %  |    |    |
+/// ---------------------
+///
+/// ... and then, when asked about foods near @, we only have to look at
+/// the buckets overlapping @'s neighbourhood.
+///
+/// Queries are conservative: they might return items lying a bit too
+/// far, but they never miss an item that's within the radius - so the
+/// caller still has to check the exact distance, as it'd do without the
+/// grid.
+#[derive(Debug)]
+crate struct SpatialGrid {
+    topology: WorldTopology,
+    origin: na::Point2<f32>,
+    extent: na::Vector2<f32>,
+    cols: usize,
+    cells: Vec<Vec<usize>>,
+    item_cells: Vec<usize>,
+}
+
+impl SpatialGrid {
+    crate fn new(
+        topology: WorldTopology,
+        positions: impl ExactSizeIterator<Item = na::Point2<f32>> + Clone,
+    ) -> Self {
+        Self::with_capacity(topology, positions, 0)
+    }
+
+    /// Creates a grid that's going to receive `capacity` more items
+    /// later, through `insert()`.
+    crate fn with_capacity(
+        topology: WorldTopology,
+        positions: impl ExactSizeIterator<Item = na::Point2<f32>> + Clone,
+        capacity: usize,
+    ) -> Self {
+        let len = positions.len();
+
+        // Aiming for around two items per cell seems to be a sweet spot
+        // between scanning too many cells and scanning too many items
+        let cols = (((len + capacity) as f32 / 2.0).sqrt().ceil() as usize).clamp(1, MAX_COLS);
+
+        let (origin, extent) = match topology {
+            // On a torus, each position is already wrapped into the unit
+            // square, so that's what we cover
+            WorldTopology::Torus => (na::Point2::new(0.0, 0.0), na::Vector2::new(1.0, 1.0)),
+
+            // Otherwise items might lie anywhere; out-of-bounds ones are
+            // going to land in the edge cells, which is fine
+            WorldTopology::Bounded | WorldTopology::Infinite => {
+                let mut min = na::Point2::new(0.0, 0.0);
+                let mut max = na::Point2::new(1.0, 1.0);
+
+                for position in positions.clone() {
+                    min = min.inf(&position);
+                    max = max.sup(&position);
+                }
+
+                (min, max - min)
+            }
+        };
+
+        let mut this = Self {
+            topology,
+            origin,
+            extent,
+            cols,
+            cells: vec![Vec::new(); cols * cols],
+            item_cells: Vec::with_capacity(len + capacity),
+        };
+
+        for position in positions {
+            this.insert(position);
+        }
+
+        this
+    }
+
+    /// Adds a new item, returning its index.
+    ///
+    /// Note that the grid doesn't grow - on bounded and infinite worlds,
+    /// items lying outside of the original area simply land in the edge
+    /// cells (which keeps queries correct, just a bit slower).
+    crate fn insert(&mut self, position: na::Point2<f32>) -> usize {
+        let item = self.item_cells.len();
+        let cell = self.cell_of(position);
+
+        self.cells[cell].push(item);
+        self.item_cells.push(cell);
+
+        item
+    }
+
+    /// Notifies the grid that given item has moved to `position`.
+    crate fn update(&mut self, item: usize, position: na::Point2<f32>) {
+        let old_cell = self.item_cells[item];
+        let new_cell = self.cell_of(position);
+
+        if old_cell == new_cell {
+            return;
+        }
+
+        let slot = self.cells[old_cell]
+            .iter()
+            .position(|&other| other == item)
+            .expect("item is not present in the grid");
+
+        self.cells[old_cell].swap_remove(slot);
+        self.cells[new_cell].push(item);
+        self.item_cells[item] = new_cell;
+    }
+
+    /// Returns items that might lie within `radius` from `center`,
+    /// sorted by their indices.
+    ///
+    /// (the ordering is important, as it makes the rest of the simulation
+    /// process foods in the same order as the brute-force approach does,
+    /// which - because floating-point addition is not associative - is
+    /// what allows both approaches to yield exactly the same results.)
+    crate fn query(&self, center: na::Point2<f32>, radius: f32) -> Vec<usize> {
+        // A tiny margin compensates for rounding errors, so that we
+        // never skip an item the caller would consider close enough
+        let radius = radius * 1.0001 + 1e-6;
+
+        let (min_col, max_col) = self.span(center.x, radius, self.origin.x, self.extent.x);
+        let (min_row, max_row) = self.span(center.y, radius, self.origin.y, self.extent.y);
+
+        let mut items = Vec::new();
+
+        for row in self.wrap_span(min_row, max_row) {
+            for col in self.wrap_span(min_col, max_col) {
+                items.extend_from_slice(&self.cells[row * self.cols + col]);
+            }
+        }
+
+        items.sort_unstable();
+        items
+    }
+
+    fn cell_of(&self, position: na::Point2<f32>) -> usize {
+        let col = self.index(position.x, self.origin.x, self.extent.x);
+        let row = self.index(position.y, self.origin.y, self.extent.y);
+
+        self.normalize(row) * self.cols + self.normalize(col)
+    }
+
+    fn index(&self, coord: f32, origin: f32, extent: f32) -> isize {
+        ((coord - origin) / extent * (self.cols as f32)).floor() as isize
+    }
+
+    fn span(&self, coord: f32, radius: f32, origin: f32, extent: f32) -> (isize, isize) {
+        (
+            self.index(coord - radius, origin, extent),
+            self.index(coord + radius, origin, extent),
+        )
+    }
+
+    /// Maps an unbounded index into an actual column / row.
+    fn normalize(&self, index: isize) -> usize {
+        let cols = self.cols as isize;
+
+        match self.topology {
+            WorldTopology::Torus => index.rem_euclid(cols) as usize,
+            WorldTopology::Bounded | WorldTopology::Infinite => index.clamp(0, cols - 1) as usize,
+        }
+    }
+
+    /// Returns all the columns / rows within given span, each one exactly
+    /// once.
+    fn wrap_span(&self, min: isize, max: isize) -> Vec<usize> {
+        let cols = self.cols as isize;
+
+        match self.topology {
+            WorldTopology::Torus if max - min + 1 >= cols => (0..self.cols).collect(),
+
+            WorldTopology::Torus => (min..=max)
+                .map(|index| index.rem_euclid(cols) as usize)
+                .collect(),
+
+            WorldTopology::Bounded | WorldTopology::Infinite => {
+                (self.normalize(min)..=self.normalize(max)).collect()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
+    use test_case::test_case;
+
+    /// Compares grid's answers with the answers of a good, old brute-force
+    /// search; since the grid is allowed to return more items than needed,
+    /// we only check that it doesn't miss anything.
+    #[test_case(WorldTopology::Torus, 0.01)]
+    #[test_case(WorldTopology::Torus, 0.25)]
+    #[test_case(WorldTopology::Torus, 0.8)]
+    #[test_case(WorldTopology::Bounded, 0.01)]
+    #[test_case(WorldTopology::Bounded, 0.25)]
+    #[test_case(WorldTopology::Infinite, 0.25)]
+    fn finds_every_nearby_item(topology: WorldTopology, radius: f32) {
+        let mut rng = ChaCha8Rng::from_seed(Default::default());
+
+        let mut positions: Vec<na::Point2<f32>> = (0..500).map(|_| rng.gen()).collect();
+        let mut grid = SpatialGrid::new(topology, positions.iter().copied());
+
+        for (item, position) in positions.iter_mut().enumerate().take(100) {
+            *position = rng.gen();
+            grid.update(item, *position);
+        }
+
+        for _ in 0..100 {
+            let center = if topology == WorldTopology::Infinite {
+                na::Point2::new(rng.gen_range(-0.5..1.5), rng.gen_range(-0.5..1.5))
+            } else {
+                rng.gen()
+            };
+
+            let expected: Vec<_> = positions
+                .iter()
+                .enumerate()
+                .filter(|(_, &position)| topology.distance(center, position) <= radius)
+                .map(|(item, _)| item)
+                .collect();
+
+            let actual = grid.query(center, radius);
+
+            for item in expected {
+                assert!(actual.contains(&item), "missed item #{}", item);
+            }
+
+            assert!(actual.windows(2).all(|items| items[0] < items[1]));
+        }
+    }
+}
@@ -0,0 +1,235 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+/// Identifies a single animal (bird or predator) for its entire life.
+///
+/// Contrary to animal's index in `World::animals()`, which gets reused
+/// by whoever takes the animal's place, ids are never reused - so they
+/// can be used to track individuals across steps, generations, replays
+/// and so on.
+///
+/// Ids are handed out by the world the animal lives in, starting from 1;
+/// animals that haven't been put into any world yet (e.g. ones created
+/// through `Animal::random()`) all share the default id of 0.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AnimalId(u64);
+
+impl AnimalId {
+    pub fn get(self) -> u64 {
+        self.0
+    }
+}
+
+impl fmt::Display for AnimalId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+/// Identifies a single food; same as `AnimalId`, but for foods.
+///
+/// When a food gets eaten and regrows somewhere else, it's considered a
+/// new food, with a new id.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FoodId(u64);
+
+impl FoodId {
+    pub fn get(self) -> u64 {
+        self.0
+    }
+}
+
+impl fmt::Display for FoodId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+/// Hands out consecutive ids, starting from 1 (0 is reserved for things
+/// that haven't been put into any world yet)
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+crate struct Counter(u64);
+
+impl Default for Counter {
+    fn default() -> Self {
+        Self(1)
+    }
+}
+
+impl Counter {
+    crate fn food(&mut self) -> FoodId {
+        FoodId(self.next())
+    }
+
+    fn next(&mut self) -> u64 {
+        let id = self.0;
+        self.0 += 1;
+        id
+    }
+}
+
+/// Family tree of all the animals that have ever lived in a world.
+///
+/// Animals that were there from the very beginning (or that came from
+/// the outside, e.g. through `Simulation::import_genome()`) have no
+/// parents; everybody else has got exactly two of them - possibly the
+/// same animal twice.
+///
+/// Elites (see: `SimulationConfig::elitism`) aren't born again when they
+/// move into the next generation - they keep their ids.
+///
+/// Note that the tree only ever grows - each newborn adds a few bytes to
+/// it, so for really long runs it's worth keeping an eye on.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Genealogy {
+    ids: Counter,
+    parents: BTreeMap<AnimalId, [AnimalId; 2]>,
+    children: BTreeMap<AnimalId, Vec<AnimalId>>,
+}
+
+impl Genealogy {
+    /// Records birth of an animal, returning its id
+    crate fn birth(&mut self, parents: Option<[AnimalId; 2]>) -> AnimalId {
+        let id = AnimalId(self.ids.next());
+
+        if let Some(parents) = parents {
+            self.parents.insert(id, parents);
+
+            for (nth, parent) in parents.iter().enumerate() {
+                // Don't count the same child twice, when both of its
+                // parents are the same animal
+                if nth == 0 || parents[0] != parents[1] {
+                    self.children.entry(*parent).or_default().push(id);
+                }
+            }
+        }
+
+        id
+    }
+
+    /// Returns parents of given animal, if it's got any
+    pub fn parents(&self, id: AnimalId) -> Option<[AnimalId; 2]> {
+        self.parents.get(&id).copied()
+    }
+
+    /// Returns children of given animal, from the oldest one
+    pub fn children(&self, id: AnimalId) -> &[AnimalId] {
+        self.children.get(&id).map_or(&[], Vec::as_slice)
+    }
+
+    /// Returns parents of given animal, parents of its parents and so on,
+    /// all the way back to the first generation
+    pub fn ancestors(&self, id: AnimalId) -> BTreeSet<AnimalId> {
+        self.walk(id, |id| {
+            self.parents(id)
+                .map_or(Vec::new(), |parents| parents.to_vec())
+        })
+    }
+
+    /// Returns children of given animal, children of its children and so
+    /// on, all the way down to the animals living right now
+    pub fn descendants(&self, id: AnimalId) -> BTreeSet<AnimalId> {
+        self.walk(id, |id| self.children(id).to_vec())
+    }
+
+    /// Returns everybody reachable from `id` through `next`, excluding
+    /// `id` itself
+    fn walk(&self, id: AnimalId, next: impl Fn(AnimalId) -> Vec<AnimalId>) -> BTreeSet<AnimalId> {
+        let mut found = BTreeSet::new();
+        let mut pending = next(id);
+
+        while let Some(id) = pending.pop() {
+            if found.insert(id) {
+                pending.extend(next(id));
+            }
+        }
+
+        found
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{EnergyConfig, EvolutionMode, Simulation, SimulationConfig};
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+    use test_case::test_case;
+
+    fn ids(ids: &[u64]) -> BTreeSet<AnimalId> {
+        ids.iter().copied().map(AnimalId).collect()
+    }
+
+    #[test]
+    fn family_tree() {
+        let mut genealogy = Genealogy::default();
+
+        let a = genealogy.birth(None);
+        let b = genealogy.birth(None);
+        let c = genealogy.birth(Some([a, b]));
+        let d = genealogy.birth(Some([c, c]));
+        let e = genealogy.birth(Some([d, b]));
+
+        assert_eq!(genealogy.parents(a), None);
+        assert_eq!(genealogy.parents(e), Some([d, b]));
+        assert_eq!(genealogy.children(c), [d]);
+        assert_eq!(genealogy.children(b), [c, e]);
+
+        assert_eq!(genealogy.ancestors(a), ids(&[]));
+        assert_eq!(genealogy.ancestors(e), ids(&[1, 2, 3, 4]));
+        assert_eq!(genealogy.descendants(a), ids(&[3, 4, 5]));
+        assert_eq!(genealogy.descendants(e), ids(&[]));
+    }
+
+    #[test_case(EvolutionMode::Generational)]
+    #[test_case(EvolutionMode::SteadyState { max_age: 50 })]
+    fn tracks_lineage(evolution_mode: EvolutionMode) {
+        let mut rng = ChaCha8Rng::from_seed(Default::default());
+
+        let config = SimulationConfig {
+            animals: 10,
+            generation_length: 50,
+            elitism: 2,
+            evolution_mode,
+            energy: Some(EnergyConfig::default()),
+            ..Default::default()
+        };
+
+        let mut sim = Simulation::from_config(config, &mut rng).unwrap();
+        let founders: BTreeSet<_> = sim.world().animals().iter().map(|a| a.id()).collect();
+
+        for _ in 0..3 {
+            sim.train(&mut rng);
+        }
+
+        let world = sim.world();
+        let living: BTreeSet<_> = world.animals().iter().map(|a| a.id()).collect();
+
+        assert_eq!(founders, ids(&(1..=10).collect::<Vec<_>>()));
+        assert_eq!(living.len(), 10);
+
+        // Everybody alive descends from the founders (or is one of them)
+        for &id in &living {
+            let ancestors = world.genealogy().ancestors(id);
+
+            assert!(founders.contains(&id) || !ancestors.is_disjoint(&founders));
+        }
+
+        for &founder in &founders {
+            for descendant in world.genealogy().descendants(founder) {
+                assert!(world.genealogy().ancestors(descendant).contains(&founder));
+            }
+        }
+
+        let mut foods: Vec<_> = world.foods().iter().map(|food| food.id()).collect();
+
+        foods.sort();
+        foods.dedup();
+
+        assert_eq!(foods.len(), world.foods().len());
+    }
+}
@@ -1,186 +1,930 @@
 #![feature(crate_visibility_modifier)]
-
 use lib_genetic_algorithm as ga;
 use nalgebra as na;
-use rand::Rng;
-use std::f32::consts::FRAC_PI_2;
+use std::cmp::Ordering;
 
 use crate::animal::AnimalIndividual;
+use crate::evolution::{preserve_elites, EvolutionState};
+use crate::grid::SpatialGrid;
+use crate::neuroevolution::StructuralGenes;
+use crate::replay::Recorder;
 
-pub use crate::animal::Animal;
-pub use crate::food::Food;
+pub use crate::animal::{Animal, AnimalState, Body, VisionChannel};
+pub use crate::archipelago::{
+    Archipelago, ArchipelagoStatistics, MigrationConfig, MigrationTopology,
+};
+pub use crate::body_config::BodyConfig;
+pub use crate::config::{ConfigError, SimulationConfig};
+pub use crate::energy::{EnergyConfig, Starvation};
+pub use crate::evolution::{Crossover, EvolutionMode, Mutation, Selection};
+pub use crate::fitness::{Fitness, FitnessFunction, LifetimeStats, WeightedSum};
+pub use crate::food::{Food, FoodConfig, FoodDistribution, Seasons};
+pub use crate::genome::{Genome, GenomeError, PopulationError};
+pub use crate::id::{AnimalId, FoodId, Genealogy};
+pub use crate::lifecycle::{DeathCause, LifecycleEvent, Species, StepOutcome};
+pub use crate::neuroevolution::NeuroevolutionConfig;
+pub use crate::observer::Observer;
+pub use crate::obstacle::{Collision, Obstacle, ObstacleConfig};
+pub use crate::placement::Placement;
+pub use crate::predator::PredatorConfig;
+pub use crate::replay::{Recording, ReplayError, Replayer};
+pub use crate::report::GenerationReport;
+pub use crate::snapshot::{Snapshot, SnapshotError};
+pub use crate::statistics::Statistics;
+pub use crate::topology::WorldTopology;
 pub use crate::world::World;
 
 mod animal;
+mod archipelago;
+mod body_config;
+mod config;
+mod energy;
+mod evolution;
+mod fitness;
 mod food;
+mod genome;
+mod grid;
+mod id;
+mod lifecycle;
+mod neuroevolution;
+mod observer;
+mod obstacle;
+mod parallel;
+mod placement;
+mod predator;
+mod replay;
+mod report;
+mod snapshot;
+mod statistics;
+mod topology;
 mod world;
 
-/// Minimum speed of a bird.
-///
-/// Keeping it above zero prevents birds from getting stuck in one place.
-const SPEED_MIN: f32 = 0.001;
-
-/// Maximum speed of a bird.
-///
-/// Keeping it "sane" prevents birds from accelerating up to infinity,
-/// which makes the simulation... unrealistic :-)
-const SPEED_MAX: f32 = 0.005;
-
-/// Speed acceleration; determines how much the brain can affect bird's
-/// speed during one step.
-///
-/// Assuming our bird is currently flying with speed=0.5, when the brain
-/// yells "stop flying!", a SPEED_ACCEL of:
-///
-/// - 0.1 = makes it take 5 steps ("5 seconds") for the bird to actually
-///         slow down to SPEED_MIN,
-///
-/// - 0.5 = makes it take 1 step for the bird to slow down to SPEED_MIN.
-///
-/// This improves simulation faithfulness, because - as in real life -
-/// it's not possible to increase speed from 1km/h to 50km/h in one
-/// instant, even if your brain very much wants to.
-const SPEED_ACCEL: f32 = 0.2;
-
-/// Ditto, but for rotation:
-///
-/// - 2 * PI = it takes one step for the bird to do a 360° rotation,
-/// - PI = it takes two steps for the bird to do a 360° rotation,
-///
-/// I've chosen PI/2, because - as our motto goes - this value seems
-/// to play nice.
-const ROTATION_ACCEL: f32 = FRAC_PI_2;
-
-/// How much `.step()`-s have to occur before we push data into the
-/// genetic algorithm.
-///
-/// Value that's too low might prevent the birds from learning, while
-/// a value that's too high will make the evolution unnecessarily
-/// slower.
-///
-/// You can treat this number as "for how many steps each bird gets
-/// to live"; 2500 was chosen with a fair dice roll.
-const GENERATION_LENGTH: usize = 2500;
-
 pub struct Simulation {
+    config: SimulationConfig,
     world: World,
-    ga: ga::GeneticAlgorithm<ga::RouletteWheelSelection>,
+    evolution: EvolutionState,
+    predator_evolution: EvolutionState,
+    fitness: Box<dyn FitnessFunction + Send>,
     age: usize,
+
+    /// Present while the simulation is being recorded (see:
+    /// `.start_recording()`)
+    recorder: Option<Recorder>,
+
+    observers: Vec<Box<dyn Observer + Send>>,
+
+    /// Birds of the generation that has just been replaced by its
+    /// offspring, as they were at the very end of their lives (see:
+    /// `.train_and_judge()`); always empty in the steady-state mode
+    retired: Vec<Animal>,
 }
 
 impl Simulation {
     pub fn random(rng: &mut dyn rand::RngCore) -> Self {
-        let world = World::random(rng);
-
-        let ga = ga::GeneticAlgorithm::new(
-            ga::RouletteWheelSelection::default(),
-            ga::UniformCrossover::default(),
-            ga::GaussianMutation::new(0.01, 0.3),
-            // ---------------------- ^--^ -^-^
-            // | Chosen with a bit of experimentation.
-            // |
-            // | Higher values can make the simulation more chaotic,
-            // | which - a bit counterintuitively - might allow for
-            // | it to discover *better* solutions; but the trade-off
-            // | is that higher values might also cause current, good
-            // | enough solutions to be discarded.
-            // ---
-        );
+        Self::from_config(SimulationConfig::default(), rng)
+            .expect("default configuration should be valid")
+    }
+
+    pub fn from_config(
+        config: SimulationConfig,
+        rng: &mut dyn rand::RngCore,
+    ) -> Result<Self, ConfigError> {
+        config.validate()?;
 
-        Self { world, ga, age: 0 }
+        let world = World::random(&config, rng);
+
+        Ok(Self::new(config, world))
+    }
+
+    /// Creates a simulation whose first birds have brains imported from
+    /// given genomes (e.g. a population saved from an earlier run, or a
+    /// hand-picked set of champions); if there are fewer genomes than
+    /// birds, the rest is random.
+    pub fn with_population(
+        genomes: Vec<Genome>,
+        config: SimulationConfig,
+        rng: &mut dyn rand::RngCore,
+    ) -> Result<Self, PopulationError> {
+        config.validate().map_err(PopulationError::InvalidConfig)?;
+
+        if genomes.len() > config.animals {
+            return Err(PopulationError::TooManyGenomes {
+                max: config.animals,
+                actual: genomes.len(),
+            });
+        }
+
+        let mut animals = Vec::with_capacity(config.animals);
+
+        for (index, genome) in genomes.into_iter().enumerate() {
+            let animal = Animal::from_genome(genome, &config, rng)
+                .map_err(|error| PopulationError::InvalidGenome { index, error })?;
+
+            animals.push(animal);
+        }
+
+        while animals.len() < config.animals {
+            animals.push(Animal::random(&config, rng));
+        }
+
+        let world = World::with_animals(animals, &config, rng);
+
+        Ok(Self::new(config, world))
+    }
+
+    /// Restores simulation previously captured via `.snapshot()`.
+    pub fn restore(snapshot: Snapshot) -> Result<Self, SnapshotError> {
+        snapshot.restore()
+    }
+
+    fn new(config: SimulationConfig, world: World) -> Self {
+        Self {
+            fitness: Box::new(config.fitness.clone()),
+            evolution: EvolutionState::new(&config.mutation),
+            predator_evolution: EvolutionState::new(&config.mutation),
+            config,
+            world,
+            age: 0,
+            recorder: None,
+            observers: Vec::new(),
+            retired: Vec::new(),
+        }
+    }
+
+    pub fn config(&self) -> &SimulationConfig {
+        &self.config
     }
 
     pub fn world(&self) -> &World {
         &self.world
     }
 
+    /// Number of generations evolved so far
+    pub fn generation(&self) -> usize {
+        self.evolution.generation()
+    }
+
+    /// Current magnitude of mutations; changes from one generation to
+    /// another when using `Mutation::Adaptive`
+    pub fn mutation_coeff(&self) -> f32 {
+        self.evolution.mutation_coeff()
+    }
+
+    /// Replaces the fitness function chosen through config with a custom
+    /// one.
+    ///
+    /// Note that custom fitness functions are not a part of snapshots -
+    /// after restoring a snapshot, you have to provide the function
+    /// again.
+    ///
+    /// (fitness functions - just like observers - have to be `Send`, so
+    /// that simulations can be trained on many threads at once; see:
+    /// `Archipelago`.)
+    pub fn set_fitness(&mut self, fitness: impl FitnessFunction + Send + 'static) {
+        self.fitness = Box::new(fitness);
+    }
+
+    /// Registers an observer that's going to get notified about what's
+    /// happening during each step (see: `Observer`); observers get
+    /// notified in the order they've been added.
+    pub fn add_observer(&mut self, observer: impl Observer + Send + 'static) {
+        self.observers.push(Box::new(observer));
+    }
+
+    /// Unregisters all of the observers
+    pub fn clear_observers(&mut self) {
+        self.observers.clear();
+    }
+
+    /// Exports brains of all the birds
+    pub fn genomes(&self) -> Vec<Genome> {
+        self.world.animals.iter().map(Animal::genome).collect()
+    }
+
+    /// Exports brain of the fittest bird
+    pub fn best_genome(&self) -> Genome {
+        self.world
+            .animals
+            .iter()
+            .max_by(|a, b| compare_fitness(&*self.fitness, a, b))
+            .expect("simulation should contain at least one bird")
+            .genome()
+    }
+
+    /// Replaces the least fit bird with a new one, created from given
+    /// genome; the newcomer gets placed just like a newborn would (see:
+    /// `SimulationConfig::bird_placement`), outside of any obstacles.
+    pub fn import_genome(
+        &mut self,
+        genome: Genome,
+        rng: &mut dyn rand::RngCore,
+    ) -> Result<(), GenomeError> {
+        self.welcome(vec![genome], rng)
+    }
+
+    /// Captures the entire state of this simulation, so that it can be
+    /// e.g. saved to a file and resumed later.
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot::new(self)
+    }
+
+    /// Starts recording each step, so that the run can be replayed later
+    /// (see: `Recording`); a keyframe gets captured every
+    /// `keyframe_interval` steps.
+    ///
+    /// Keyframes are what makes seeking fast, but they also take most of
+    /// the recording's space - a few hundred steps apart seems like a
+    /// fair compromise.
+    ///
+    /// Fails when `keyframe_interval` is zero.
+    pub fn start_recording(&mut self, keyframe_interval: usize) -> Result<(), ConfigError> {
+        self.recorder = Some(Recorder::new(self, keyframe_interval)?);
+        Ok(())
+    }
+
+    /// Stops recording, returning everything recorded since
+    /// `.start_recording()`
+    pub fn stop_recording(&mut self) -> Option<Recording> {
+        self.recorder.take().map(Recorder::finish)
+    }
+
     /// Performs a single step - a single second, so to say - of our
     /// simulation.
-    pub fn step(&mut self, rng: &mut dyn rand::RngCore) -> Option<ga::Statistics> {
-        self.process_collisions(rng);
-        self.process_brains();
+    pub fn step(&mut self, rng: &mut dyn rand::RngCore) -> Option<StepOutcome> {
+        if let Some(mut recorder) = self.recorder.take() {
+            let outcome = recorder.record(self, rng);
+            self.recorder = Some(recorder);
+
+            return outcome;
+        }
+
+        self.process_foods(rng);
+
+        let mut grid = self.food_grid();
+
+        self.process_collisions(grid.as_mut(), rng);
+        self.process_brains(grid.as_ref());
         self.process_movements();
 
         self.age += 1;
 
-        if self.age > GENERATION_LENGTH {
-            Some(self.evolve(rng))
+        let outcome = match self.config.evolution_mode {
+            EvolutionMode::Generational => {
+                if self.age > self.config.generation_length {
+                    Some(StepOutcome::Generation(self.evolve(rng)))
+                } else {
+                    None
+                }
+            }
+
+            EvolutionMode::SteadyState { max_age } => {
+                let events = self.process_lifecycle(max_age, rng);
+
+                if events.is_empty() {
+                    None
+                } else {
+                    Some(StepOutcome::Lifecycle(events))
+                }
+            }
+        };
+
+        observer::notify_step(&mut self.observers, outcome.as_ref(), &self.world);
+
+        outcome
+    }
+
+    /// Fast-forward 'till the end of the current generation.
+    ///
+    /// In the steady-state mode, where there are no generations, this
+    /// simply performs `generation_length` steps and summarizes animals
+    /// that are alive at the end.
+    pub fn train(&mut self, rng: &mut dyn rand::RngCore) -> Statistics {
+        if let EvolutionMode::SteadyState { .. } = self.config.evolution_mode {
+            for _ in 0..self.config.generation_length {
+                self.step(rng);
+            }
+
+            let birds: Vec<_> = self
+                .world
+                .animals
+                .iter()
+                .map(|animal| AnimalIndividual::from_animal(animal, &*self.fitness))
+                .collect();
+
+            let mut stats = self
+                .evolution
+                .update(&self.config.mutation, &fitness_of(&birds));
+
+            if self.config.predators.is_some() {
+                let predators: Vec<_> = self
+                    .world
+                    .predators
+                    .iter()
+                    .map(|predator| AnimalIndividual::from_animal(predator, &PREDATOR_FITNESS))
+                    .collect();
+
+                stats.predators = Some(Box::new(
+                    self.predator_evolution
+                        .update(&self.config.mutation, &fitness_of(&predators)),
+                ));
+            }
+
+            for observer in &mut self.observers {
+                observer.on_generation_end(&stats);
+            }
+
+            return stats;
+        }
+
+        loop {
+            if let Some(StepOutcome::Generation(summary)) = self.step(rng) {
+                return summary;
+            }
+        }
+    }
+
+    /// Trains for a generation, just like `.train()` does, but also
+    /// summarizes how the birds have been doing (see: `GenerationReport`).
+    ///
+    /// (it's a separate function, since some of the numbers - especially
+    /// the genome diversity - take a while to compute.)
+    pub fn train_with_report(&mut self, rng: &mut dyn rand::RngCore) -> GenerationReport {
+        let timer = report::Timer::start();
+        let (stats, summary) = self.train_and_judge(rng, |this, animals| {
+            report::Summary::new(&this.config, &*this.fitness, animals)
+        });
+
+        summary.into_report(stats, timer.elapsed())
+    }
+
+    /// Trains for a generation (see: `.train()`) and picks `count` of its
+    /// fittest birds, so that they can emigrate to another simulation
+    /// (see: `Archipelago`).
+    crate fn train_and_pick(
+        &mut self,
+        count: usize,
+        rng: &mut dyn rand::RngCore,
+    ) -> (Statistics, Vec<Genome>) {
+        self.train_and_judge(rng, |this, animals| this.fittest_genomes(animals, count))
+    }
+
+    /// Trains for a generation (see: `.train()`), calling `judge` on the
+    /// birds once they're done.
+    ///
+    /// In the generational mode the birds get judged as they were right
+    /// before being replaced by their children - i.e. exactly the way
+    /// they've been judged by `Statistics`.
+    fn train_and_judge<T>(
+        &mut self,
+        rng: &mut dyn rand::RngCore,
+        judge: impl FnOnce(&Self, &[Animal]) -> T,
+    ) -> (Statistics, T) {
+        let stats = self.train(rng);
+
+        let animals = match self.config.evolution_mode {
+            EvolutionMode::Generational => &self.retired,
+            EvolutionMode::SteadyState { .. } => &self.world.animals,
+        };
+
+        (stats, judge(self, animals))
+    }
+
+    /// Exports brains of `count` fittest of given birds, starting from the
+    /// best one
+    fn fittest_genomes(&self, animals: &[Animal], count: usize) -> Vec<Genome> {
+        let mut animals: Vec<_> = animals.iter().collect();
+
+        animals.sort_by(|a, b| compare_fitness(&*self.fitness, b, a));
+
+        animals
+            .into_iter()
+            .take(count)
+            .map(Animal::genome)
+            .collect()
+    }
+
+    /// Replaces the weakest birds with new ones, created from given
+    /// genomes (see: `Archipelago`, `.import_genome()`).
+    ///
+    /// Right after a generation ends, all of the birds are equally weak
+    /// (none of them have eaten anything yet) - in that case the birds
+    /// at the end get replaced first, since the front is where the
+    /// elites live (see: `SimulationConfig::elitism`).
+    crate fn welcome(
+        &mut self,
+        genomes: Vec<Genome>,
+        rng: &mut dyn rand::RngCore,
+    ) -> Result<(), GenomeError> {
+        let fitness = &*self.fitness;
+        let animals = &self.world.animals;
+        let mut weakest: Vec<_> = (0..animals.len()).collect();
+
+        weakest
+            .sort_by(|&a, &b| compare_fitness(fitness, &animals[a], &animals[b]).then(b.cmp(&a)));
+
+        for (id, genome) in weakest.into_iter().zip(genomes) {
+            let animal = Animal::from_genome(genome, &self.config, rng)?;
+
+            self.world.animals[id] = Animal {
+                id: self.world.genealogy.birth(None),
+                ..animal
+            };
+
+            World::place_newborn(&self.config, &mut self.world.animals, id, rng);
+        }
+
+        Ok(())
+    }
+
+    /// Builds a spatial index of foods, if it's enabled.
+    ///
+    /// Since foods get eaten (and thus moved) at pretty much every step,
+    /// it's easier to rebuild the grid from scratch than to keep it in
+    /// sync between steps.
+    fn food_grid(&self) -> Option<SpatialGrid> {
+        if self.config.spatial_index {
+            Some(SpatialGrid::new(
+                self.config.topology,
+                self.world.foods.iter().map(|food| food.position),
+            ))
         } else {
             None
         }
     }
 
-    /// Fast-forward 'till the end of the current generation
-    pub fn train(&mut self, rng: &mut dyn rand::RngCore) -> ga::Statistics {
-        loop {
-            if let Some(summary) = self.step(rng) {
-                return summary;
+    /// Regrows foods, moves food patches around and changes the seasons
+    /// (see: `SimulationConfig::food`).
+    fn process_foods(&mut self, rng: &mut dyn rand::RngCore) {
+        let config = match &self.config.food {
+            Some(config) => config,
+            None => return,
+        };
+
+        config.drift(&mut self.world.patches, rng);
+
+        let abundance = config.abundance(self.world.clock);
+        let count = self.world.foods.len() as f32;
+
+        for (id, food) in self.world.foods.iter_mut().enumerate() {
+            food.regrowth = food.regrowth.saturating_sub(1);
+
+            // As the winter comes, foods go dormant one by one, starting
+            // from the last one
+            food.dormant = (id as f32 + 0.5) / count > abundance;
+        }
+
+        self.world.clock += 1;
+    }
+
+    fn process_collisions(
+        &mut self,
+        mut grid: Option<&mut SpatialGrid>,
+        rng: &mut dyn rand::RngCore,
+    ) {
+        for (animal_id, animal) in self.world.animals.iter_mut().enumerate() {
+            if !animal.is_alive() {
+                continue;
+            }
+
+            // Larger birds reach further
+            let eat_range = self.config.eat_range * animal.body.size;
+
+            let foods = match &grid {
+                Some(grid) => grid.query(animal.position, eat_range),
+                None => (0..self.world.foods.len()).collect(),
+            };
+
+            for id in foods {
+                let food = &self.world.foods[id];
+
+                if !food.is_available() {
+                    continue;
+                }
+
+                let distance = self
+                    .config
+                    .topology
+                    .distance(animal.position, food.position);
+
+                if distance <= eat_range {
+                    let nutrition = food.nutrition;
+
+                    // Regrown food gets a new id, so the eaten one's has
+                    // to be remembered up front
+                    let food_id = food.id;
+
+                    animal.stats.food_eaten += 1;
+                    animal.stats.nutrition += nutrition;
+                    animal.feed(&self.config, nutrition);
+
+                    animal
+                        .stats
+                        .time_to_first_food
+                        .get_or_insert(animal.stats.lifetime);
+
+                    World::regrow_food(
+                        &self.config,
+                        &mut self.world.foods,
+                        &self.world.patches,
+                        &mut self.world.food_ids,
+                        grid.as_deref_mut(),
+                        id,
+                        rng,
+                    );
+
+                    for observer in &mut self.observers {
+                        observer.on_food_eaten(animal_id, animal.id, id, food_id);
+                    }
+                }
+            }
+        }
+
+        if let Some(config) = &self.config.predators {
+            let topology = self.config.topology;
+            let generational = self.config.evolution_mode == EvolutionMode::Generational;
+
+            for (predator_id, predator) in self.world.predators.iter_mut().enumerate() {
+                // Each predator catches at most one bird per step - the
+                // nearest one - so that a flock flying by doesn't get
+                // wiped out at once
+                let prey = self
+                    .world
+                    .animals
+                    .iter_mut()
+                    .enumerate()
+                    .filter(|(_, bird)| bird.is_alive())
+                    .map(|(id, bird)| {
+                        let distance = topology.distance(predator.position, bird.position);
+                        (id, bird, distance)
+                    })
+                    .filter(|(_, _, distance)| *distance <= config.catch_range)
+                    .min_by(|(_, _, a), (_, _, b)| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+
+                if let Some((prey_id, prey, _)) = prey {
+                    for observer in &mut self.observers {
+                        observer.on_catch(predator_id, predator.id, prey_id, prey.id);
+
+                        // (in the steady-state mode deaths get reported
+                        // once the dead get replaced, see: `.replace_dead()`)
+                        if generational {
+                            observer.on_death(
+                                Species::Bird,
+                                prey_id,
+                                prey.id,
+                                DeathCause::Predation,
+                            );
+                        }
+                    }
+
+                    prey.state = AnimalState::Caught;
+                    predator.stats.food_eaten += 1;
+
+                    predator
+                        .stats
+                        .time_to_first_food
+                        .get_or_insert(predator.stats.lifetime);
+                }
             }
         }
     }
 
-    fn process_collisions(&mut self, rng: &mut dyn rand::RngCore) {
-        for animal in &mut self.world.animals {
-            for food in &mut self.world.foods {
-                let distance = na::distance(&animal.position, &food.position);
+    fn process_brains(&mut self, foods: Option<&SpatialGrid>) {
+        let birds = self.bird_grid();
+
+        // First everybody takes a look around, and only then everybody
+        // moves - so that it doesn't matter who goes first (and so that
+        // everybody can look around at the same time, in parallel)
+        let (config, world) = (&self.config, &self.world);
+        let look = |animal: &Animal, me: Option<usize>| {
+            Self::look(config, world, animal, me, foods, birds.as_ref())
+        };
+
+        let bird_responses = parallel::map(&world.animals, |id, animal| {
+            if animal.is_alive() {
+                Some(animal.brain.nn.propagate(look(animal, Some(id))))
+            } else {
+                None
+            }
+        });
+
+        let predator_responses = parallel::map(&world.predators, |_, predator| {
+            predator.brain.nn.propagate(look(predator, None))
+        });
+
+        let generational = self.config.evolution_mode == EvolutionMode::Generational;
+
+        for (id, (animal, response)) in self
+            .world
+            .animals
+            .iter_mut()
+            .zip(bird_responses)
+            .enumerate()
+        {
+            if let Some(response) = response {
+                let limits = SpeedLimits::of_bird(&self.config, animal);
+                let (speed, rotation) = steer(animal, &response, limits);
 
-                if distance <= 0.01 {
-                    animal.satiation += 1;
-                    food.position = rng.gen()
+                animal.exert(speed, rotation, &self.config);
+
+                if generational && animal.state == AnimalState::Dead {
+                    for observer in &mut self.observers {
+                        observer.on_death(Species::Bird, id, animal.id, DeathCause::Starvation);
+                    }
                 }
             }
         }
+
+        if let Some(config) = &self.config.predators {
+            let limits = SpeedLimits::of_predators(config);
+
+            for (predator, response) in self.world.predators.iter_mut().zip(predator_responses) {
+                steer(predator, &response, limits);
+            }
+        }
     }
 
-    fn process_brains(&mut self) {
-        for animal in &mut self.world.animals {
-            let vision =
-                animal
-                    .eye
-                    .process_vision(animal.position, animal.rotation, &self.world.foods);
+    /// Builds a spatial index of birds, if it's enabled and if anybody's
+    /// going to look at the birds at all
+    fn bird_grid(&self) -> Option<SpatialGrid> {
+        let needed =
+            self.config.predators.is_some() || self.config.vision.contains(&VisionChannel::Birds);
 
-            let response = animal.brain.nn.propagate(vision);
+        if self.config.spatial_index && needed {
+            Some(SpatialGrid::new(
+                self.config.topology,
+                self.world.animals.iter().map(|animal| animal.position),
+            ))
+        } else {
+            None
+        }
+    }
 
-            // ---
-            // | Limits number to given range.
-            // -------------------- v---v
-            let speed = response[0].clamp(-SPEED_ACCEL, SPEED_ACCEL);
+    /// Returns what given animal sees, channel after channel; `me` is the
+    /// animal's index, if it's a bird (so that it doesn't see itself).
+    ///
+    /// (it doesn't take `&self`, so that it can be called from many
+    /// threads at once - see: the `parallel` feature.)
+    fn look(
+        config: &SimulationConfig,
+        world: &World,
+        animal: &Animal,
+        me: Option<usize>,
+        foods: Option<&SpatialGrid>,
+        birds: Option<&SpatialGrid>,
+    ) -> Vec<f32> {
+        let topology = config.topology;
+        let obstacles = config.obstacles.as_ref();
+        let eye = &animal.eye;
+        let mut vision = Vec::with_capacity(eye.inputs());
 
-            let rotation = response[1].clamp(-ROTATION_ACCEL, ROTATION_ACCEL);
+        for channel in eye.channels() {
+            let cells = match channel {
+                VisionChannel::Food => match foods {
+                    Some(grid) => eye.process_vision(
+                        topology,
+                        animal.position,
+                        animal.rotation,
+                        grid.query(animal.position, eye.fov_range())
+                            .into_iter()
+                            .map(|id| &world.foods[id])
+                            .filter(|food| food.is_available()),
+                        obstacles,
+                    ),
 
-            // Our speed & rotation here are *relative* - that is: when
-            // they are equal to zero, what the brain says is "keep
-            // flying as you are now", not "stop flying".
-            //
-            // Both values being relative is crucial, because our bird's
-            // brain doesn't know its own speed and rotation*, meaning
-            // that it fundamentally cannot return absolute values.
-            //
-            // * they'd have to be provided as separate inputs to the
-            //   neural network, which would make the evolution process
-            //   waaay longer, if even possible.
+                    None => eye.process_vision(
+                        topology,
+                        animal.position,
+                        animal.rotation,
+                        world.foods.iter().filter(|food| food.is_available()),
+                        obstacles,
+                    ),
+                },
 
-            animal.speed = (animal.speed + speed).clamp(SPEED_MIN, SPEED_MAX);
+                VisionChannel::Birds => {
+                    let candidates = match birds {
+                        Some(grid) => grid.query(animal.position, eye.fov_range()),
+                        None => (0..world.animals.len()).collect(),
+                    };
 
-            animal.rotation = na::Rotation2::new(animal.rotation.angle() + rotation);
+                    eye.process_targets(
+                        topology,
+                        animal.position,
+                        animal.rotation,
+                        candidates
+                            .into_iter()
+                            .filter(|&id| Some(id) != me)
+                            .map(|id| &world.animals[id])
+                            .filter(|bird| bird.is_alive())
+                            .map(|bird| bird.position),
+                        obstacles,
+                    )
+                }
 
-            // (btw, there is no need for ROTATION_MIN or ROTATION_MAX,
-            // because rotation automatically wraps from 2*PI back to 0 -
-            // we've already witnessed that when we were testing eyes,
-            // inside `mod different_rotations { ... }`.)
+                VisionChannel::Predators => eye.process_targets(
+                    topology,
+                    animal.position,
+                    animal.rotation,
+                    world.predators.iter().map(|predator| predator.position),
+                    obstacles,
+                ),
+
+                VisionChannel::Obstacles => match obstacles {
+                    Some(obstacles) => {
+                        eye.process_obstacles(topology, animal.position, animal.rotation, obstacles)
+                    }
+
+                    None => vec![0.0; eye.cells()],
+                },
+            };
+
+            vision.extend(cells);
         }
+
+        vision
     }
 
     fn process_movements(&mut self) {
-        for animal in &mut self.world.animals {
-            animal.position += animal.rotation * na::Vector2::new(animal.speed, 0.0);
+        let config = &self.config;
 
-            animal.position.x = na::wrap(animal.position.x, 0.0, 1.0);
-            animal.position.y = na::wrap(animal.position.y, 0.0, 1.0);
+        let process = |animal: &mut Animal| {
+            if !animal.is_alive() {
+                return;
+            }
+
+            let step = animal.rotation * na::Vector2::new(animal.speed, 0.0);
+
+            match &config.obstacles {
+                Some(obstacles) => obstacles.move_through(
+                    config.topology,
+                    &mut animal.position,
+                    &mut animal.rotation,
+                    step,
+                ),
+
+                None => animal.position += step,
+            }
+
+            animal.stats.distance_travelled += animal.speed;
+            animal.stats.lifetime += 1;
+
+            config
+                .topology
+                .confine(&mut animal.position, &mut animal.rotation);
+        };
+
+        parallel::for_each_mut(&mut self.world.animals, process);
+        parallel::for_each_mut(&mut self.world.predators, process);
+    }
+
+    /// Replaces animals that have died (of old age, starvation or
+    /// predation) with offspring of the ones that are still alive.
+    fn process_lifecycle(
+        &mut self,
+        max_age: usize,
+        rng: &mut dyn rand::RngCore,
+    ) -> Vec<LifecycleEvent> {
+        let mut events = Vec::new();
+
+        self.replace_dead(Species::Bird, max_age, rng, &mut events);
+
+        if self.config.predators.is_some() {
+            self.replace_dead(Species::Predator, max_age, rng, &mut events);
         }
+
+        events
     }
 
-    fn evolve(&mut self, rng: &mut dyn rand::RngCore) -> ga::Statistics {
+    fn replace_dead(
+        &mut self,
+        species: Species,
+        max_age: usize,
+        rng: &mut dyn rand::RngCore,
+        events: &mut Vec<LifecycleEvent>,
+    ) {
+        let (animals, evolution, fitness): (_, _, &dyn FitnessFunction) = match species {
+            Species::Bird => (&mut self.world.animals, &self.evolution, &*self.fitness),
+
+            Species::Predator => (
+                &mut self.world.predators,
+                &self.predator_evolution,
+                &PREDATOR_FITNESS,
+            ),
+        };
+
+        let mut deaths = Vec::new();
+        let mut living = Vec::new();
+
+        for (id, animal) in animals.iter().enumerate() {
+            match animal.state {
+                AnimalState::Alive => (),
+
+                AnimalState::Frozen | AnimalState::Dead => {
+                    deaths.push((id, DeathCause::Starvation));
+                    continue;
+                }
+
+                AnimalState::Caught => {
+                    deaths.push((id, DeathCause::Predation));
+                    continue;
+                }
+            }
+
+            if animal.stats.lifetime >= max_age {
+                deaths.push((id, DeathCause::OldAge));
+            }
+
+            // Animals dying of old age still get to be parents (otherwise
+            // a population that's born at once, and thus dies at once,
+            // would have no one to breed with); starved or caught ones
+            // don't
+            living.push(id);
+        }
+
+        if deaths.is_empty() {
+            return;
+        }
+
+        let structure = match species {
+            Species::Bird => StructuralGenes::of_birds(&self.config),
+            Species::Predator => None,
+        };
+
+        let population: Vec<_> = living
+            .iter()
+            .map(|&id| AnimalIndividual::from_animal(&animals[id], fitness))
+            .collect();
+
+        // Parents might die (and get replaced) while we're still breeding,
+        // so their ids have to be remembered up front
+        let parent_ids: Vec<_> = living.iter().map(|&id| animals[id].id).collect();
+
+        for (id, cause) in deaths {
+            events.push(LifecycleEvent::Death {
+                species,
+                animal: id,
+                id: animals[id].id,
+                cause,
+            });
+
+            let (chromosome, parents, lineage) = if population.is_empty() {
+                (None, None, None)
+            } else {
+                let (chromosome, [parent_a, parent_b]) = evolution.breed(
+                    &self.config.selection,
+                    &self.config.crossover,
+                    &self.config.mutation,
+                    structure.as_ref(),
+                    rng,
+                    &population,
+                );
+
+                (
+                    Some(chromosome),
+                    Some([living[parent_a], living[parent_b]]),
+                    Some([parent_ids[parent_a], parent_ids[parent_b]]),
+                )
+            };
+
+            let animal = match (species, chromosome, &self.config.predators) {
+                (Species::Bird, Some(chromosome), _) => {
+                    Animal::from_chromosome(chromosome, &self.config, rng)
+                }
+
+                (Species::Bird, None, _) => Animal::random(&self.config, rng),
+
+                (Species::Predator, Some(chromosome), Some(_)) => {
+                    Animal::predator_from_chromosome(chromosome, &self.config, rng)
+                }
+
+                (Species::Predator, None, Some(config)) => Animal::random_predator(config, rng),
+
+                (Species::Predator, _, None) => unreachable!("predators are disabled"),
+            };
+
+            let newborn = self.world.genealogy.birth(lineage);
+
+            animals[id] = Animal {
+                id: newborn,
+                ..animal
+            };
+
+            match species {
+                Species::Bird => World::place_newborn(&self.config, animals, id, rng),
+                Species::Predator => {
+                    World::free_from_obstacles(&self.config, &mut animals[id..=id])
+                }
+            }
+
+            events.push(LifecycleEvent::Birth {
+                species,
+                animal: id,
+                id: newborn,
+                parents,
+            });
+        }
+    }
+
+    fn evolve(&mut self, rng: &mut dyn rand::RngCore) -> Statistics {
         self.age = 0;
 
         // Transforms `Vec<Animal>` to `Vec<AnimalIndividual>`
@@ -188,22 +932,344 @@ impl Simulation {
             .world
             .animals
             .iter()
-            .map(AnimalIndividual::from_animal)
+            .map(|animal| AnimalIndividual::from_animal(animal, &*self.fitness))
             .collect();
 
         // Evolves this `Vec<AnimalIndividual>`
-        let (evolved_population, stats) = self.ga.evolve(rng, &current_population);
+        let evolved_population = evolve_population(
+            &mut self.evolution,
+            &self.config,
+            StructuralGenes::of_birds(&self.config),
+            self.config.elitism,
+            &current_population,
+            rng,
+        );
+
+        let mut stats = self
+            .evolution
+            .update(&self.config.mutation, &fitness_of(&current_population));
 
         // Transforms `Vec<AnimalIndividual>` back into `Vec<Animal>`
-        self.world.animals = evolved_population
+        let (config, genealogy) = (&self.config, &mut self.world.genealogy);
+
+        let animals = evolved_population
             .into_iter()
-            .map(|individual| individual.into_animal(rng))
+            .map(|individual| individual.into_animal(config, genealogy, rng))
             .collect();
 
-        for food in &mut self.world.foods {
-            food.position = rng.gen();
+        let previous = std::mem::replace(&mut self.world.animals, animals);
+
+        self.notify_births(Species::Bird, &previous);
+        self.retired = previous;
+
+        World::place_birds(&self.config, &mut self.world.animals, rng);
+
+        self.world.foods = World::scatter_foods(
+            &self.config,
+            &self.world.animals,
+            &self.world.patches,
+            &mut self.world.food_ids,
+            rng,
+        );
+
+        // Predators evolve separately, in their own population
+        if let Some(config) = &self.config.predators {
+            let current_population: Vec<_> = self
+                .world
+                .predators
+                .iter()
+                .map(|predator| AnimalIndividual::from_animal(predator, &PREDATOR_FITNESS))
+                .collect();
+
+            let evolved_population = evolve_population(
+                &mut self.predator_evolution,
+                &self.config,
+                None,
+                self.config.elitism.min(config.count),
+                &current_population,
+                rng,
+            );
+
+            let predator_stats = self
+                .predator_evolution
+                .update(&self.config.mutation, &fitness_of(&current_population));
+
+            let (sim_config, genealogy) = (&self.config, &mut self.world.genealogy);
+
+            let predators = evolved_population
+                .into_iter()
+                .map(|individual| individual.into_predator(sim_config, genealogy, rng))
+                .collect();
+
+            let previous = std::mem::replace(&mut self.world.predators, predators);
+
+            self.notify_births(Species::Predator, &previous);
+
+            World::free_from_obstacles(&self.config, &mut self.world.predators);
+
+            stats.predators = Some(Box::new(predator_stats));
         }
 
         stats
     }
+
+    /// Tells observers about animals that have been born while evolving
+    /// the whole population (see: `.evolve()`); elites aren't reported,
+    /// since they move into the next generation as they are.
+    ///
+    /// Parents are identified by their indices in `previous`.
+    fn notify_births(&mut self, species: Species, previous: &[Animal]) {
+        if self.observers.is_empty() {
+            return;
+        }
+
+        let animals = match species {
+            Species::Bird => &self.world.animals,
+            Species::Predator => &self.world.predators,
+        };
+
+        let index_of = |id: AnimalId| previous.iter().position(|animal| animal.id == id);
+
+        for (id, animal) in animals.iter().enumerate() {
+            if index_of(animal.id).is_some() {
+                continue;
+            }
+
+            let parents = self
+                .world
+                .genealogy
+                .parents(animal.id)
+                .and_then(|[a, b]| Some([index_of(a)?, index_of(b)?]));
+
+            for observer in &mut self.observers {
+                observer.on_birth(species, id, animal.id, parents);
+            }
+        }
+    }
+}
+
+/// Predators are judged by the number of birds they've caught
+const PREDATOR_FITNESS: Fitness = Fitness::Satiation;
+
+/// Speed limits of a single species
+#[derive(Clone, Copy)]
+struct SpeedLimits {
+    speed_min: f32,
+    speed_max: f32,
+    speed_accel: f32,
+    rotation_accel: f32,
+}
+
+impl SpeedLimits {
+    /// Each bird might have its own maximum speed (see:
+    /// `BodyConfig::speed_max`)
+    fn of_bird(config: &SimulationConfig, animal: &Animal) -> Self {
+        Self {
+            speed_min: config.speed_min,
+            speed_max: animal.body.speed_max,
+            speed_accel: config.speed_accel,
+            rotation_accel: config.rotation_accel,
+        }
+    }
+
+    fn of_predators(config: &PredatorConfig) -> Self {
+        Self {
+            speed_min: config.speed_min,
+            speed_max: config.speed_max,
+            speed_accel: config.speed_accel,
+            rotation_accel: config.rotation_accel,
+        }
+    }
+}
+
+/// Applies brain's response to animal's speed and rotation; returns the
+/// new speed and the rotation that's been made.
+fn steer(animal: &mut Animal, response: &[f32], limits: SpeedLimits) -> (f32, f32) {
+    // ---
+    // | Limits number to given range.
+    // -------------------- v---v
+    let speed = response[0].clamp(-limits.speed_accel, limits.speed_accel);
+    let rotation = response[1].clamp(-limits.rotation_accel, limits.rotation_accel);
+
+    // Our speed & rotation here are *relative* - that is: when
+    // they are equal to zero, what the brain says is "keep
+    // flying as you are now", not "stop flying".
+    //
+    // Both values being relative is crucial, because our bird's
+    // brain doesn't know its own speed and rotation*, meaning
+    // that it fundamentally cannot return absolute values.
+    //
+    // * they'd have to be provided as separate inputs to the
+    //   neural network, which would make the evolution process
+    //   waaay longer, if even possible.
+
+    let new_speed = (animal.speed + speed).clamp(limits.speed_min, limits.speed_max);
+
+    animal.stats.energy_spent += (new_speed - animal.speed).abs();
+    animal.stats.turning_effort += rotation.abs();
+
+    animal.speed = new_speed;
+    animal.rotation = na::Rotation2::new(animal.rotation.angle() + rotation);
+
+    // (btw, there is no need for `rotation_min` or `rotation_max`,
+    // because rotation automatically wraps from 2*PI back to 0 -
+    // we've already witnessed that when we were testing eyes,
+    // inside `mod different_rotations { ... }`.)
+
+    (new_speed, rotation)
+}
+
+/// Runs a single generation of the genetic algorithm, keeping `elitism`
+/// fittest individuals intact.
+fn evolve_population(
+    evolution: &mut EvolutionState,
+    config: &SimulationConfig,
+    structure: Option<StructuralGenes>,
+    elitism: usize,
+    population: &[AnimalIndividual],
+    rng: &mut dyn rand::RngCore,
+) -> Vec<AnimalIndividual> {
+    // Same as `ga::GeneticAlgorithm::evolve()`, but remembering who's
+    // whose parent (see: `Genealogy`)
+    let mut evolved_population: Vec<_> = (0..population.len())
+        .map(|_| {
+            let (chromosome, [parent_a, parent_b]) = evolution.breed(
+                &config.selection,
+                &config.crossover,
+                &config.mutation,
+                structure.as_ref(),
+                rng,
+                population,
+            );
+
+            AnimalIndividual::child_of(chromosome, [&population[parent_a], &population[parent_b]])
+        })
+        .collect();
+
+    preserve_elites(population, &mut evolved_population, elitism);
+
+    evolved_population
+}
+
+fn fitness_of(population: &[AnimalIndividual]) -> Vec<f32> {
+    population.iter().map(ga::Individual::fitness).collect()
+}
+
+fn compare_fitness(fitness: &dyn FitnessFunction, a: &Animal, b: &Animal) -> Ordering {
+    let a = fitness.fitness(&a.stats);
+    let b = fitness.fitness(&b.stats);
+
+    a.partial_cmp(&b).unwrap_or(Ordering::Equal)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+    use test_case::test_case;
+
+    /// Runs a small simulation and returns everything there is to know
+    /// about its final state
+    fn simulate(
+        topology: WorldTopology,
+        vision: &[VisionChannel],
+        spatial_index: bool,
+    ) -> Vec<f32> {
+        let mut rng = ChaCha8Rng::from_seed(Default::default());
+
+        let config = SimulationConfig {
+            topology,
+            spatial_index,
+            vision: vision.to_vec(),
+            animals: 40,
+            foods: 80,
+            eat_range: 0.02,
+            generation_length: 60,
+            ..Default::default()
+        };
+
+        let mut sim = Simulation::from_config(config, &mut rng).unwrap();
+        let mut state = Vec::new();
+
+        for _ in 0..2 {
+            let stats = sim.train(&mut rng);
+
+            state.extend(vec![
+                stats.min_fitness(),
+                stats.max_fitness(),
+                stats.avg_fitness(),
+            ]);
+        }
+
+        for _ in 0..20 {
+            sim.step(&mut rng);
+        }
+
+        for animal in sim.world().animals() {
+            state.extend(vec![
+                animal.position.x,
+                animal.position.y,
+                animal.rotation.angle(),
+                animal.speed,
+                animal.satiation() as f32,
+            ]);
+        }
+
+        for food in sim.world().foods() {
+            state.extend(vec![food.position.x, food.position.y]);
+        }
+
+        state
+    }
+
+    #[test_case(WorldTopology::Torus, &[VisionChannel::Food])]
+    #[test_case(WorldTopology::Bounded, &[VisionChannel::Food])]
+    #[test_case(WorldTopology::Infinite, &[VisionChannel::Food])]
+    #[test_case(WorldTopology::Torus, &[VisionChannel::Food, VisionChannel::Birds])]
+    fn spatial_index_yields_identical_results(topology: WorldTopology, vision: &[VisionChannel]) {
+        assert_eq!(
+            simulate(topology, vision, true),
+            simulate(topology, vision, false)
+        );
+    }
+
+    #[test]
+    fn birds_see_each_other() {
+        let mut rng = ChaCha8Rng::from_seed(Default::default());
+
+        let config = SimulationConfig {
+            animals: 2,
+            foods: 1,
+            eye_cells: 3,
+            vision: vec![VisionChannel::Food, VisionChannel::Birds],
+            ..Default::default()
+        };
+
+        let mut sim = Simulation::from_config(config, &mut rng).unwrap();
+
+        // Food lies far away, so nobody sees it; the first bird sees the
+        // second one right in front of itself, while the second one sees
+        // nothing, since the first one is behind its back
+        sim.world.foods[0].position = na::Point2::new(0.1, 0.9);
+        sim.world.animals[0].position = na::Point2::new(0.5, 0.5);
+        sim.world.animals[0].rotation = na::Rotation2::new(0.0);
+        sim.world.animals[1].position = na::Point2::new(0.6, 0.5);
+        sim.world.animals[1].rotation = na::Rotation2::new(0.0);
+
+        let vision = |id: usize| {
+            Simulation::look(
+                &sim.config,
+                &sim.world,
+                &sim.world.animals[id],
+                Some(id),
+                None,
+                None,
+            )
+        };
+
+        assert_eq!(vision(0)[..3], [0.0, 0.0, 0.0]);
+        assert!(vision(0)[4] > 0.5);
+        assert_eq!(vision(1), vec![0.0; 6]);
+    }
 }
@@ -0,0 +1,148 @@
+use crate::{AnimalId, Statistics};
+
+/// What's happened during a single `Simulation::step()`
+#[derive(Clone, Debug, PartialEq)]
+pub enum StepOutcome {
+    /// Generation has come to an end and the whole population has been
+    /// replaced by offspring (see: `EvolutionMode::Generational`)
+    Generation(Statistics),
+
+    /// Some birds have died and have been replaced by newborns (see:
+    /// `EvolutionMode::SteadyState`)
+    Lifecycle(Vec<LifecycleEvent>),
+}
+
+impl StepOutcome {
+    pub fn statistics(&self) -> Option<&Statistics> {
+        match self {
+            Self::Generation(stats) => Some(stats),
+            Self::Lifecycle(_) => None,
+        }
+    }
+
+    pub fn events(&self) -> &[LifecycleEvent] {
+        match self {
+            Self::Generation(_) => &[],
+            Self::Lifecycle(events) => events,
+        }
+    }
+}
+
+/// A single birth or death.
+///
+/// Animals are identified by their indices in `World::animals()` (or
+/// `World::predators()`, depending on the species) and by their ids;
+/// since a newborn takes the place of the animal that has just died, each
+/// `Death` is followed by a `Birth` at the same index - but with a new id.
+///
+/// (by the time the events get returned, the dead are gone, so it's the
+/// id that tells who has actually died.)
+#[derive(Clone, Debug, PartialEq)]
+pub enum LifecycleEvent {
+    Death {
+        species: Species,
+        animal: usize,
+        id: AnimalId,
+        cause: DeathCause,
+    },
+
+    Birth {
+        species: Species,
+        animal: usize,
+
+        /// Id of the newborn; ids of its parents can be found through
+        /// `World::genealogy()`
+        id: AnimalId,
+
+        /// Indices of the parents, or `None` if all the animals of given
+        /// species have starved to death (or got caught) and so the newborn
+        /// had to be created at random.
+        ///
+        /// Note that parents might've died in the very same step, as the
+        /// animals that die of old age still get to breed.
+        parents: Option<[usize; 2]>,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Species {
+    Bird,
+    Predator,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeathCause {
+    /// Animal has lived for `max_age` steps
+    OldAge,
+
+    /// Bird has run out of energy
+    Starvation,
+
+    /// Bird has been caught by a predator
+    Predation,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{EnergyConfig, EvolutionMode, Simulation, SimulationConfig};
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    #[test]
+    fn steady_state_replaces_birds_one_by_one() {
+        let mut rng = ChaCha8Rng::from_seed(Default::default());
+
+        let config = SimulationConfig {
+            animals: 10,
+            foods: 50,
+            eat_range: 0.05,
+            evolution_mode: EvolutionMode::SteadyState { max_age: 40 },
+            energy: Some(EnergyConfig::default()),
+            ..Default::default()
+        };
+
+        let mut sim = Simulation::from_config(config, &mut rng).unwrap();
+        let mut events = Vec::new();
+
+        for _ in 0..100 {
+            if let Some(outcome) = sim.step(&mut rng) {
+                assert_eq!(outcome.statistics(), None);
+                events.extend(outcome.events().to_vec());
+            }
+        }
+
+        // Each bird dies of old age twice: after 40 and 80 steps
+        assert_eq!(events.len(), 2 * 2 * 10);
+
+        for pair in events.chunks(2) {
+            match pair {
+                [LifecycleEvent::Death {
+                    species: Species::Bird,
+                    animal: dead,
+                    id: dead_id,
+                    cause,
+                }, LifecycleEvent::Birth {
+                    species: Species::Bird,
+                    animal: born,
+                    id: born_id,
+                    parents,
+                }] => {
+                    assert_eq!(dead, born);
+                    assert_ne!(dead_id, born_id);
+                    assert_eq!(*cause, DeathCause::OldAge);
+                    assert!(parents.is_some());
+                }
+
+                _ => panic!("unexpected events: {:?}", pair),
+            }
+        }
+
+        for animal in sim.world().animals() {
+            assert!(animal.stats().lifetime() < 40);
+        }
+
+        assert_eq!(sim.train(&mut rng).generation(), 0);
+        assert_eq!(sim.generation(), 1);
+    }
+}
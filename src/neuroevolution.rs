@@ -0,0 +1,571 @@
+use lib_genetic_algorithm as ga;
+use rand::seq::IteratorRandom;
+use rand::Rng;
+
+use crate::animal::{Brain, BrainShape};
+use crate::config::ensure;
+use crate::{ConfigError, Crossover, SimulationConfig};
+
+/// Makes birds evolve not only their brains' weights, but also their
+/// structure - in the spirit of NEAT ("NeuroEvolution of Augmenting
+/// Topologies").
+///
+/// Usually all brains are shaped the same: `eye_cells` inputs (per each
+/// vision channel), twice as much hidden neurons, and two outputs. With
+/// neuroevolution enabled, each bird's chromosome additionally encodes
+/// the shape of its brain, which can then change from one generation to
+/// another:
+///
+/// - hidden neurons can get added and removed,
+/// - connections (i.e. weights) can get disabled and enabled again,
+/// - eyes can gain or lose a cell.
+///
+/// Since chromosomes of differently-shaped brains have different
+/// lengths, they cannot be crossed over gene by gene - instead, genes of
+/// the second parent get lined up against the first parent's brain,
+/// neuron by neuron (which serves the same purpose as NEAT's innovation
+/// numbers), and only then get crossed over.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct NeuroevolutionConfig {
+    /// Maximum number of cells an eye can evolve; note that birds start
+    /// with `SimulationConfig::eye_cells` cells
+    pub max_eye_cells: usize,
+
+    /// Maximum number of hidden neurons a brain can evolve
+    pub max_hidden: usize,
+
+    /// Chance of a child getting an additional hidden neuron.
+    ///
+    /// A new neuron starts with just a single incoming and a single
+    /// outgoing connection, so that it doesn't disturb the brain too much.
+    pub add_neuron_chance: f32,
+
+    /// Chance of a child losing one of its hidden neurons
+    pub remove_neuron_chance: f32,
+
+    /// Chance of a child getting one of its disabled connections enabled,
+    /// with a random weight
+    pub add_connection_chance: f32,
+
+    /// Chance of a child getting one of its connections disabled.
+    ///
+    /// Disabled connections have weight of zero and aren't affected by
+    /// `SimulationConfig::mutation` - they stay disabled until they get
+    /// enabled through `add_connection_chance`.
+    pub remove_connection_chance: f32,
+
+    /// Chance of a child's eye gaining or losing a cell; existing
+    /// connections get stretched over the new cells, so that the bird
+    /// still sees the world roughly the same way
+    pub eye_cells_chance: f32,
+}
+
+impl NeuroevolutionConfig {
+    crate fn validate(&self, eye_cells: usize, channels: usize) -> Result<(), ConfigError> {
+        ensure(
+            self.max_eye_cells >= eye_cells,
+            "neuroevolution.max_eye_cells",
+            "cannot be lower than eye_cells",
+        )?;
+
+        ensure(
+            self.max_hidden >= BrainShape::new(eye_cells, channels).hidden,
+            "neuroevolution.max_hidden",
+            "cannot be lower than the initial number of hidden neurons",
+        )?;
+
+        let chances = [
+            (self.add_neuron_chance, "neuroevolution.add_neuron_chance"),
+            (
+                self.remove_neuron_chance,
+                "neuroevolution.remove_neuron_chance",
+            ),
+            (
+                self.add_connection_chance,
+                "neuroevolution.add_connection_chance",
+            ),
+            (
+                self.remove_connection_chance,
+                "neuroevolution.remove_connection_chance",
+            ),
+            (self.eye_cells_chance, "neuroevolution.eye_cells_chance"),
+        ];
+
+        for (chance, field) in chances.iter().copied() {
+            ensure(
+                (0.0..=1.0).contains(&chance),
+                field,
+                "must be within [0, 1]",
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for NeuroevolutionConfig {
+    fn default() -> Self {
+        Self {
+            max_eye_cells: 15,
+            max_hidden: 64,
+            add_neuron_chance: 0.05,
+            remove_neuron_chance: 0.02,
+            add_connection_chance: 0.1,
+            remove_connection_chance: 0.05,
+            eye_cells_chance: 0.02,
+        }
+    }
+}
+
+/// Tells the genetic operators where to find the brain inside a
+/// chromosome whose shape is evolvable (see: `ChromosomeLayout`)
+#[derive(Clone, Debug)]
+crate struct StructuralGenes {
+    config: NeuroevolutionConfig,
+
+    /// Number of body genes preceding the brain
+    body: usize,
+
+    /// Number of vision channels
+    channels: usize,
+}
+
+impl StructuralGenes {
+    /// Returns `None` when birds' brains have fixed shape
+    crate fn of_birds(config: &SimulationConfig) -> Option<Self> {
+        let neuroevolution = config.neuroevolution.as_ref()?;
+
+        Some(Self {
+            config: neuroevolution.clone(),
+            body: config.body.as_ref().map_or(0, |body| body.genes().len()),
+            channels: config.vision.len(),
+        })
+    }
+
+    /// Returns the brain encoded in given chromosome
+    crate fn brain(&self, chromosome: &ga::Chromosome) -> NeuralGenes {
+        self.decode(chromosome).1
+    }
+
+    fn decode(&self, chromosome: &ga::Chromosome) -> (Vec<f32>, NeuralGenes) {
+        let genes: Vec<_> = chromosome.iter().copied().collect();
+        let (body, brain) = genes.split_at(self.body);
+
+        (body.to_vec(), NeuralGenes::decode(brain, self.channels))
+    }
+
+    fn encode(&self, body: Vec<f32>, brain: &NeuralGenes) -> ga::Chromosome {
+        body.into_iter().chain(brain.encode()).collect()
+    }
+}
+
+/// Brain genes grouped by neurons, which makes it easy to line up genes
+/// of brains of different shapes
+#[derive(Clone, Debug, PartialEq)]
+crate struct NeuralGenes {
+    eye_cells: usize,
+    channels: usize,
+
+    /// Each hidden neuron: bias, followed by a weight per each input
+    hidden: Vec<Vec<f32>>,
+
+    /// Each output neuron: bias, followed by a weight per each hidden
+    /// neuron
+    outputs: Vec<Vec<f32>>,
+}
+
+impl NeuralGenes {
+    /// Decodes brain's shape followed by its weights, in the order used
+    /// by `lib_neural_network::Network::from_weights()`
+    fn decode(genes: &[f32], channels: usize) -> Self {
+        let shape = BrainShape::decode(genes).expect("chromosome should describe a valid brain");
+        let genes = &genes[BrainShape::GENES..];
+
+        assert_eq!(genes.len(), shape.brain_len(channels));
+
+        let [inputs, hidden, outputs] = Brain::layers(shape.eye_cells * channels, shape.hidden);
+        let (hidden_genes, output_genes) = genes.split_at((inputs + 1) * hidden);
+
+        Self {
+            eye_cells: shape.eye_cells,
+            channels,
+            hidden: hidden_genes
+                .chunks(inputs + 1)
+                .map(|neuron| neuron.to_vec())
+                .collect(),
+            outputs: output_genes
+                .chunks(hidden + 1)
+                .take(outputs)
+                .map(|neuron| neuron.to_vec())
+                .collect(),
+        }
+    }
+
+    fn encode(&self) -> Vec<f32> {
+        let shape = BrainShape {
+            eye_cells: self.eye_cells,
+            hidden: self.hidden.len(),
+        };
+
+        shape
+            .encode()
+            .iter()
+            .copied()
+            .chain(self.hidden.iter().chain(&self.outputs).flatten().copied())
+            .collect()
+    }
+
+    fn inputs(&self) -> usize {
+        self.eye_cells * self.channels
+    }
+
+    /// Returns distance between this brain and `other`, treating their
+    /// weights as points in space; `other` gets reshaped into this
+    /// brain's shape first (see: `.aligned_to()`), so genes that only one
+    /// of the brains has don't count.
+    crate fn distance(&self, other: &Self) -> f32 {
+        let other = other.aligned_to(self);
+
+        self.hidden
+            .iter()
+            .chain(&self.outputs)
+            .flatten()
+            .zip(other.hidden.iter().chain(&other.outputs).flatten())
+            .map(|(a, b)| (a - b).powi(2))
+            .sum::<f32>()
+            .sqrt()
+    }
+
+    /// Changes the number of eye cells, stretching (or squashing) the
+    /// existing connections over the new cells
+    fn resize_eye(&mut self, eye_cells: usize) {
+        let old_cells = self.eye_cells;
+
+        for neuron in &mut self.hidden {
+            let mut resized = vec![neuron[0]];
+
+            for channel in 0..self.channels {
+                for cell in 0..eye_cells {
+                    // Maps center of the new cell into the old one
+                    let old_cell = (2 * cell + 1) * old_cells / (2 * eye_cells);
+
+                    resized.push(neuron[1 + channel * old_cells + old_cell]);
+                }
+            }
+
+            *neuron = resized;
+        }
+
+        self.eye_cells = eye_cells;
+    }
+
+    /// Returns a copy of this brain, reshaped into `other`'s shape; genes
+    /// that this brain is missing (e.g. because `other` has more hidden
+    /// neurons) are taken from `other`.
+    fn aligned_to(&self, other: &Self) -> Self {
+        let mut this = self.clone();
+
+        this.resize_eye(other.eye_cells);
+
+        let hidden = other
+            .hidden
+            .iter()
+            .enumerate()
+            .map(|(id, neuron)| this.hidden.get(id).unwrap_or(neuron).clone())
+            .collect();
+
+        let outputs = other
+            .outputs
+            .iter()
+            .zip(&this.outputs)
+            .map(|(theirs, ours)| {
+                theirs
+                    .iter()
+                    .enumerate()
+                    .map(|(id, &gene)| ours.get(id).copied().unwrap_or(gene))
+                    .collect()
+            })
+            .collect();
+
+        Self {
+            hidden,
+            outputs,
+            ..this
+        }
+    }
+
+    fn add_neuron(&mut self, rng: &mut dyn rand::RngCore) {
+        let mut neuron = vec![0.0; self.inputs() + 1];
+        let input = rng.gen_range(1..neuron.len());
+
+        neuron[input] = rng.gen_range(-1.0..=1.0);
+
+        self.hidden.push(neuron);
+
+        for output in &mut self.outputs {
+            output.push(0.0);
+        }
+
+        let output = rng.gen_range(0..self.outputs.len());
+        let weight = rng.gen_range(-1.0..=1.0);
+
+        if let Some(gene) = self.outputs[output].last_mut() {
+            *gene = weight;
+        }
+    }
+
+    fn remove_neuron(&mut self, rng: &mut dyn rand::RngCore) {
+        let neuron = rng.gen_range(0..self.hidden.len());
+
+        self.hidden.remove(neuron);
+
+        for output in &mut self.outputs {
+            output.remove(1 + neuron);
+        }
+    }
+
+    /// Returns weights of all the connections (i.e. all genes except for
+    /// the biases)
+    fn connections(&mut self) -> impl Iterator<Item = &mut f32> {
+        self.hidden
+            .iter_mut()
+            .chain(&mut self.outputs)
+            .flat_map(|neuron| neuron.iter_mut().skip(1))
+    }
+}
+
+/// Crossover that's able to deal with brains of different shapes (see:
+/// `NeuroevolutionConfig`); when shapes are fixed, it's the same as the
+/// underlying `Crossover`.
+#[derive(Clone, Debug)]
+crate struct StructuralCrossover {
+    crate crossover: Crossover,
+    crate structure: Option<StructuralGenes>,
+}
+
+impl ga::CrossoverMethod for StructuralCrossover {
+    fn crossover(
+        &self,
+        rng: &mut dyn rand::RngCore,
+        parent_a: &ga::Chromosome,
+        parent_b: &ga::Chromosome,
+    ) -> ga::Chromosome {
+        let structure = match &self.structure {
+            Some(structure) => structure,
+            None => return self.crossover.crossover(rng, parent_a, parent_b),
+        };
+
+        // Child inherits the shape of the first parent - so we reshape
+        // the second parent to match it, and then cross them over as usual
+        let (_, brain_a) = structure.decode(parent_a);
+        let (body_b, brain_b) = structure.decode(parent_b);
+        let parent_b = structure.encode(body_b, &brain_b.aligned_to(&brain_a));
+
+        self.crossover.crossover(rng, parent_a, &parent_b)
+    }
+}
+
+/// Mutation that - apart from nudging the genes - changes the shape of
+/// the brains (see: `NeuroevolutionConfig`); when shapes are fixed, it's
+/// the same as `ga::GaussianMutation`.
+#[derive(Clone, Debug)]
+crate struct StructuralMutation {
+    crate chance: f32,
+    crate coeff: f32,
+    crate structure: Option<StructuralGenes>,
+}
+
+impl StructuralMutation {
+    fn nudge(&self, rng: &mut dyn rand::RngCore, gene: &mut f32) {
+        let sign = if rng.gen_bool(0.5) { -1.0 } else { 1.0 };
+
+        if rng.gen_bool(self.chance as _) {
+            *gene += sign * self.coeff * rng.gen::<f32>();
+        }
+    }
+}
+
+impl ga::MutationMethod for StructuralMutation {
+    fn mutate(&self, rng: &mut dyn rand::RngCore, child: &mut ga::Chromosome) {
+        let structure = match &self.structure {
+            Some(structure) => structure,
+
+            None => {
+                return ga::GaussianMutation::new(self.chance, self.coeff).mutate(rng, child);
+            }
+        };
+
+        let config = &structure.config;
+        let (mut body, mut brain) = structure.decode(child);
+
+        for gene in &mut body {
+            self.nudge(rng, gene);
+        }
+
+        for neuron in brain.hidden.iter_mut().chain(&mut brain.outputs) {
+            for (id, gene) in neuron.iter_mut().enumerate() {
+                // Disabled connections stay disabled
+                if id == 0 || *gene != 0.0 {
+                    self.nudge(rng, gene);
+                }
+            }
+        }
+
+        if rng.gen_bool(config.eye_cells_chance as _) {
+            let eye_cells = if rng.gen_bool(0.5) {
+                brain.eye_cells + 1
+            } else {
+                brain.eye_cells.saturating_sub(1)
+            };
+
+            if (1..=config.max_eye_cells).contains(&eye_cells) {
+                brain.resize_eye(eye_cells);
+            }
+        }
+
+        if rng.gen_bool(config.add_neuron_chance as _) && brain.hidden.len() < config.max_hidden {
+            brain.add_neuron(rng);
+        }
+
+        if rng.gen_bool(config.remove_neuron_chance as _) && brain.hidden.len() > 1 {
+            brain.remove_neuron(rng);
+        }
+
+        if rng.gen_bool(config.add_connection_chance as _) {
+            let weight = rng.gen_range(-1.0..=1.0);
+
+            if let Some(gene) = brain.connections().filter(|gene| **gene == 0.0).choose(rng) {
+                *gene = weight;
+            }
+        }
+
+        if rng.gen_bool(config.remove_connection_chance as _) {
+            if let Some(gene) = brain.connections().filter(|gene| **gene != 0.0).choose(rng) {
+                *gene = 0.0;
+            }
+        }
+
+        *child = structure.encode(body, &brain);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Simulation, VisionChannel};
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+    use std::collections::HashSet;
+
+    /// Brain with two eye cells and given hidden neurons, followed by a
+    /// single output neuron with given genes (the other output neuron
+    /// is all zeros)
+    fn brain(hidden: &[[f32; 3]], output: &[f32]) -> NeuralGenes {
+        NeuralGenes {
+            eye_cells: 2,
+            channels: 1,
+            hidden: hidden.iter().map(|neuron| neuron.to_vec()).collect(),
+            outputs: vec![output.to_vec(), vec![0.0; output.len()]],
+        }
+    }
+
+    #[test]
+    fn aligning_brains() {
+        let small = brain(&[[1.0, 1.0, 1.0]], &[1.0, 1.0]);
+
+        let large = brain(
+            &[[2.0, 2.0, 2.0], [3.0, 3.0, 3.0], [4.0, 4.0, 4.0]],
+            &[2.0, 2.0, 3.0, 4.0],
+        );
+
+        assert_eq!(
+            small.aligned_to(&large),
+            brain(
+                &[[1.0, 1.0, 1.0], [3.0, 3.0, 3.0], [4.0, 4.0, 4.0]],
+                &[1.0, 1.0, 3.0, 4.0],
+            ),
+        );
+
+        assert_eq!(
+            large.aligned_to(&small),
+            brain(&[[2.0, 2.0, 2.0]], &[2.0, 2.0]),
+        );
+
+        // Chromosomes survive the round trip
+        assert_eq!(NeuralGenes::decode(&large.encode(), 1), large);
+    }
+
+    #[test]
+    fn measuring_distance() {
+        let small = brain(&[[1.0, 1.0, 1.0]], &[1.0, 1.0]);
+
+        let large = brain(
+            &[[2.0, 2.0, 2.0], [3.0, 3.0, 3.0], [4.0, 4.0, 4.0]],
+            &[2.0, 2.0, 3.0, 4.0],
+        );
+
+        // Only the first hidden neuron and the first output's bias and
+        // weight are shared; everything else is ignored
+        approx::assert_relative_eq!(small.distance(&large), 5.0f32.sqrt());
+        approx::assert_relative_eq!(large.distance(&small), 5.0f32.sqrt());
+        approx::assert_relative_eq!(large.distance(&large), 0.0);
+    }
+
+    #[test]
+    fn resizing_eye() {
+        let mut brain = brain(&[[0.5, 1.0, 2.0]], &[0.0, 0.0]);
+
+        brain.resize_eye(4);
+        assert_eq!(brain.hidden, vec![vec![0.5, 1.0, 1.0, 2.0, 2.0]]);
+
+        brain.resize_eye(1);
+        assert_eq!(brain.hidden, vec![vec![0.5, 2.0]]);
+    }
+
+    #[test]
+    fn brains_change_their_shape() {
+        let mut rng = ChaCha8Rng::from_seed(Default::default());
+
+        let config = SimulationConfig {
+            animals: 20,
+            eye_cells: 3,
+            vision: vec![VisionChannel::Food, VisionChannel::Birds],
+            generation_length: 50,
+            neuroevolution: Some(NeuroevolutionConfig {
+                max_eye_cells: 5,
+                max_hidden: 16,
+                add_neuron_chance: 0.5,
+                remove_neuron_chance: 0.3,
+                add_connection_chance: 0.5,
+                remove_connection_chance: 0.5,
+                eye_cells_chance: 0.3,
+            }),
+            ..Default::default()
+        };
+
+        let mut sim = Simulation::from_config(config.clone(), &mut rng).unwrap();
+
+        for _ in 0..5 {
+            sim.train(&mut rng);
+        }
+
+        let genomes = sim.genomes();
+
+        let shapes: HashSet<_> = genomes
+            .iter()
+            .map(|genome| (genome.eye_cells(), genome.hidden()))
+            .collect();
+
+        assert!(shapes.len() > 1);
+
+        for (eye_cells, hidden) in shapes {
+            assert!((1..=5).contains(&eye_cells));
+            assert!((1..=16).contains(&hidden));
+        }
+
+        // Genomes of any shape can be imported back
+        Simulation::with_population(genomes, config, &mut rng).unwrap();
+    }
+}
@@ -0,0 +1,359 @@
+use crate::{
+    AnimalId, DeathCause, FoodId, LifecycleEvent, Species, Statistics, StepOutcome, World,
+};
+
+/// Something that wants to know what's going on inside the simulation -
+/// a logger, a metrics collector, a visualisation and so on.
+///
+/// All of the callbacks do nothing by default, so you only have to
+/// implement the ones you care about; observers get registered through
+/// `Simulation::add_observer()`.
+///
+/// Animals and foods are identified both by their indices in
+/// `World::animals()` (or `World::predators()`) and `World::foods()`, and
+/// by their ids (see: `AnimalId`, `FoodId`). Indices get reused - e.g. by
+/// the time a food's been reported as eaten, another food has already
+/// taken its place - so to track individuals over time, use the ids.
+#[allow(unused_variables)]
+pub trait Observer {
+    /// Called at the end of each step, after everything else
+    fn on_step(&mut self, world: &World) {}
+
+    /// Called when a bird eats a food; by the time it's called, the food
+    /// has already regrown somewhere else, under a new id - `food_id` is
+    /// the id of the food that's been eaten
+    fn on_food_eaten(&mut self, animal: usize, animal_id: AnimalId, food: usize, food_id: FoodId) {}
+
+    /// Called when a predator catches a bird
+    fn on_catch(&mut self, predator: usize, predator_id: AnimalId, bird: usize, bird_id: AnimalId) {
+    }
+
+    /// Called when an animal gets born (see: `LifecycleEvent::Birth`).
+    ///
+    /// In the generational mode it's called for each child right after
+    /// the new generation has been bred (elites don't get born again,
+    /// though), with parents being indices into the previous generation.
+    fn on_birth(
+        &mut self,
+        species: Species,
+        animal: usize,
+        id: AnimalId,
+        parents: Option<[usize; 2]>,
+    ) {
+    }
+
+    /// Called when an animal dies (see: `LifecycleEvent::Death`).
+    ///
+    /// In the generational mode it's called as soon as a bird starves to
+    /// death or gets caught - nobody dies of old age there, and frozen
+    /// birds (see: `Starvation::Freeze`) are still considered alive.
+    fn on_death(&mut self, species: Species, animal: usize, id: AnimalId, cause: DeathCause) {}
+
+    /// Called when a generation comes to an end (see:
+    /// `StepOutcome::Generation`) or, in the steady-state mode, when
+    /// `Simulation::train()` finishes
+    fn on_generation_end(&mut self, stats: &Statistics) {}
+}
+
+/// Tells observers about step's outcome and about the world it's left
+crate fn notify_step(
+    observers: &mut [Box<dyn Observer + Send>],
+    outcome: Option<&StepOutcome>,
+    world: &World,
+) {
+    for observer in observers {
+        match outcome {
+            Some(StepOutcome::Generation(stats)) => observer.on_generation_end(stats),
+
+            Some(StepOutcome::Lifecycle(events)) => {
+                for event in events {
+                    match *event {
+                        LifecycleEvent::Death {
+                            species,
+                            animal,
+                            id,
+                            cause,
+                        } => observer.on_death(species, animal, id, cause),
+
+                        LifecycleEvent::Birth {
+                            species,
+                            animal,
+                            id,
+                            parents,
+                        } => observer.on_birth(species, animal, id, parents),
+                    }
+                }
+            }
+
+            None => (),
+        }
+
+        observer.on_step(world);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        AnimalState, EnergyConfig, EvolutionMode, PredatorConfig, Simulation, SimulationConfig,
+        VisionChannel,
+    };
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Default)]
+    struct Counts {
+        steps: usize,
+        foods: Vec<FoodId>,
+        catches: usize,
+        births: usize,
+        deaths: Vec<DeathCause>,
+        dead: Vec<AnimalId>,
+        generations: usize,
+    }
+
+    struct Counter(Arc<Mutex<Counts>>);
+
+    impl Observer for Counter {
+        fn on_step(&mut self, _: &World) {
+            self.0.lock().unwrap().steps += 1;
+        }
+
+        fn on_food_eaten(&mut self, _: usize, _: AnimalId, _: usize, food: FoodId) {
+            self.0.lock().unwrap().foods.push(food);
+        }
+
+        fn on_catch(&mut self, _: usize, _: AnimalId, _: usize, _: AnimalId) {
+            self.0.lock().unwrap().catches += 1;
+        }
+
+        fn on_birth(&mut self, _: Species, _: usize, _: AnimalId, _: Option<[usize; 2]>) {
+            self.0.lock().unwrap().births += 1;
+        }
+
+        fn on_death(&mut self, _: Species, _: usize, id: AnimalId, cause: DeathCause) {
+            let mut counts = self.0.lock().unwrap();
+
+            counts.deaths.push(cause);
+            counts.dead.push(id);
+        }
+
+        fn on_generation_end(&mut self, _: &Statistics) {
+            self.0.lock().unwrap().generations += 1;
+        }
+    }
+
+    #[test]
+    fn observers_see_everything() {
+        let mut rng = ChaCha8Rng::from_seed(Default::default());
+
+        let config = SimulationConfig {
+            animals: 10,
+            eat_range: 0.05,
+            generation_length: 20,
+            ..Default::default()
+        };
+
+        let mut sim = Simulation::from_config(config, &mut rng).unwrap();
+        let counts = Arc::new(Mutex::new(Counts::default()));
+
+        sim.add_observer(Counter(Arc::clone(&counts)));
+        sim.add_observer(Counter(Arc::clone(&counts)));
+
+        for _ in 0..20 {
+            sim.step(&mut rng);
+        }
+
+        let foods: usize = sim
+            .world()
+            .animals()
+            .iter()
+            .map(|animal| animal.stats().food_eaten())
+            .sum();
+
+        // Each callback gets called twice, once per observer
+        assert_eq!(counts.lock().unwrap().steps, 2 * 20);
+        assert_eq!(counts.lock().unwrap().foods.len(), 2 * foods);
+        assert_eq!(counts.lock().unwrap().generations, 0);
+
+        sim.step(&mut rng);
+
+        assert_eq!(counts.lock().unwrap().generations, 2);
+    }
+
+    #[test]
+    fn observers_see_births_and_deaths() {
+        let mut rng = ChaCha8Rng::from_seed(Default::default());
+
+        let config = SimulationConfig {
+            animals: 10,
+            evolution_mode: EvolutionMode::SteadyState { max_age: 50 },
+            energy: Some(EnergyConfig::default()),
+            ..Default::default()
+        };
+
+        let mut sim = Simulation::from_config(config, &mut rng).unwrap();
+        let counts = Arc::new(Mutex::new(Counts::default()));
+
+        sim.add_observer(Counter(Arc::clone(&counts)));
+
+        let mut events = 0;
+
+        for _ in 0..200 {
+            if let Some(outcome) = sim.step(&mut rng) {
+                events += outcome.events().len();
+            }
+        }
+
+        let counts = counts.lock().unwrap();
+
+        assert!(!counts.deaths.is_empty());
+        assert_eq!(counts.births, counts.deaths.len());
+        assert_eq!(counts.births + counts.deaths.len(), events);
+    }
+
+    #[test]
+    fn observers_see_ids_of_the_eaten_and_the_dead() {
+        let mut rng = ChaCha8Rng::from_seed(Default::default());
+
+        let config = SimulationConfig {
+            animals: 10,
+            eat_range: 0.05,
+            evolution_mode: EvolutionMode::SteadyState { max_age: 20 },
+            ..Default::default()
+        };
+
+        let mut sim = Simulation::from_config(config, &mut rng).unwrap();
+        let counts = Arc::new(Mutex::new(Counts::default()));
+
+        sim.add_observer(Counter(Arc::clone(&counts)));
+
+        let mut eaten = 0;
+
+        for _ in 0..50 {
+            let foods: Vec<_> = sim.world().foods().iter().map(|food| food.id()).collect();
+            let animals: Vec<_> = sim.world().animals().iter().map(|a| a.id()).collect();
+
+            sim.step(&mut rng);
+
+            let mut counts = counts.lock().unwrap();
+
+            // Eaten foods and dead birds were there before the step and
+            // aren't there anymore - their slots are taken by someone else
+            for food in counts.foods.drain(..) {
+                eaten += 1;
+
+                assert!(foods.contains(&food));
+                assert!(sim.world().foods().iter().all(|other| other.id() != food));
+            }
+
+            for animal in counts.dead.drain(..) {
+                assert!(animals.contains(&animal));
+                assert!(sim.world().animals().iter().all(|a| a.id() != animal));
+            }
+        }
+
+        // Everybody dies of old age twice: after 20 and 40 steps
+        assert!(eaten > 0);
+        assert_eq!(counts.lock().unwrap().deaths.len(), 2 * 10);
+    }
+
+    #[test]
+    fn observers_see_generational_starvation_and_births() {
+        let mut rng = ChaCha8Rng::from_seed(Default::default());
+
+        let config = SimulationConfig {
+            animals: 10,
+            generation_length: 100,
+            elitism: 2,
+            energy: Some(EnergyConfig {
+                initial: 0.1,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let mut sim = Simulation::from_config(config, &mut rng).unwrap();
+        let counts = Arc::new(Mutex::new(Counts::default()));
+
+        sim.add_observer(Counter(Arc::clone(&counts)));
+
+        for _ in 0..100 {
+            sim.step(&mut rng);
+        }
+
+        let dead = sim
+            .world()
+            .animals()
+            .iter()
+            .filter(|animal| animal.state() == AnimalState::Dead)
+            .count();
+
+        assert!(dead > 0);
+        assert_eq!(
+            counts.lock().unwrap().deaths,
+            vec![DeathCause::Starvation; dead]
+        );
+        assert_eq!(counts.lock().unwrap().births, 0);
+
+        sim.step(&mut rng);
+
+        // Everybody but the elites gets born again
+        assert_eq!(counts.lock().unwrap().births, 10 - 2);
+        assert_eq!(counts.lock().unwrap().generations, 1);
+    }
+
+    #[test]
+    fn observers_see_generational_catches() {
+        let mut rng = ChaCha8Rng::from_seed(Default::default());
+
+        let config = SimulationConfig {
+            animals: 10,
+            generation_length: 100,
+            vision: vec![VisionChannel::Food, VisionChannel::Predators],
+            predators: Some(PredatorConfig {
+                count: 2,
+                catch_range: 0.2,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let mut sim = Simulation::from_config(config, &mut rng).unwrap();
+        let counts = Arc::new(Mutex::new(Counts::default()));
+
+        sim.add_observer(Counter(Arc::clone(&counts)));
+        sim.train(&mut rng);
+
+        let counts = counts.lock().unwrap();
+
+        assert!(counts.catches > 0);
+        assert_eq!(counts.deaths, vec![DeathCause::Predation; counts.catches]);
+
+        // Both birds and predators get born again
+        assert_eq!(counts.births, 10 + 2);
+    }
+
+    #[test]
+    fn observers_see_steady_state_generations() {
+        let mut rng = ChaCha8Rng::from_seed(Default::default());
+
+        let config = SimulationConfig {
+            animals: 10,
+            generation_length: 20,
+            evolution_mode: EvolutionMode::SteadyState { max_age: 50 },
+            ..Default::default()
+        };
+
+        let mut sim = Simulation::from_config(config, &mut rng).unwrap();
+        let counts = Arc::new(Mutex::new(Counts::default()));
+
+        sim.add_observer(Counter(Arc::clone(&counts)));
+        sim.train(&mut rng);
+        sim.train(&mut rng);
+
+        assert_eq!(counts.lock().unwrap().generations, 2);
+    }
+}
@@ -0,0 +1,466 @@
+use nalgebra as na;
+use std::cmp::Ordering;
+
+use crate::config::ensure;
+use crate::{ConfigError, WorldTopology};
+
+/// How far from obstacle's surface birds stop, so that rounding errors
+/// don't let them slip through it during the next step
+const SURFACE_MARGIN: f32 = 1e-4;
+
+/// Static obstacles - walls, rocks and such - that birds cannot fly
+/// through, nor see through.
+///
+/// Food and birds that'd end up inside an obstacle (e.g. because they
+/// got placed there at random) get pushed out onto its surface; squeezing
+/// two obstacles close together might push them into the other one,
+/// though, so try to keep some space between them.
+///
+/// On a torus, obstacles should be smaller than half of the world, so
+/// that it's unambiguous which side of the edge they lie on.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct ObstacleConfig {
+    pub shapes: Vec<Obstacle>,
+
+    /// What happens when a bird flies into an obstacle
+    pub collision: Collision,
+}
+
+impl ObstacleConfig {
+    crate fn validate(&self) -> Result<(), ConfigError> {
+        ensure(
+            !self.shapes.is_empty(),
+            "obstacles.shapes",
+            "must not be empty",
+        )?;
+
+        for shape in &self.shapes {
+            shape.validate()?;
+        }
+
+        Ok(())
+    }
+
+    /// Moves given bird by `step`, making it slide or bounce off of
+    /// whatever obstacle lies in its way.
+    crate fn move_through(
+        &self,
+        topology: WorldTopology,
+        position: &mut na::Point2<f32>,
+        rotation: &mut na::Rotation2<f32>,
+        step: na::Vector2<f32>,
+    ) {
+        let hit = match self.hit(topology, *position, step) {
+            Some(hit) => hit,
+
+            None => {
+                *position += step;
+                return;
+            }
+        };
+
+        let len = step.norm();
+        let before = step * (hit.t - SURFACE_MARGIN / len).max(0.0);
+        let after = step - before;
+
+        *position += before;
+
+        match self.collision {
+            Collision::Slide => {
+                // Whatever's left of the step gets projected onto the
+                // obstacle's surface; when that'd make us fly into
+                // another obstacle (think: corners), we just stop
+                let along = after - hit.normal * after.dot(&hit.normal);
+
+                if self.hit(topology, *position, along).is_none() {
+                    *position += along;
+                }
+            }
+
+            Collision::Bounce => {
+                let reflected = step - hit.normal * (2.0 * step.dot(&hit.normal));
+
+                *rotation = na::Rotation2::rotation_between(&na::Vector2::x(), &reflected);
+            }
+        }
+    }
+
+    /// Returns whether there's an obstacle between `from` and the point
+    /// lying at `vec` from it
+    crate fn occludes(
+        &self,
+        topology: WorldTopology,
+        from: na::Point2<f32>,
+        vec: na::Vector2<f32>,
+    ) -> bool {
+        self.hit(topology, from, vec).is_some()
+    }
+
+    /// Returns which fraction of `vec` (going from `from`) is free of
+    /// obstacles - i.e. `1.0` when there's nothing in the way
+    crate fn clearance(
+        &self,
+        topology: WorldTopology,
+        from: na::Point2<f32>,
+        vec: na::Vector2<f32>,
+    ) -> f32 {
+        self.hit(topology, from, vec).map_or(1.0, |hit| hit.t)
+    }
+
+    /// Returns given point, moved out of any obstacle it's lying in
+    crate fn push_out(&self, point: na::Point2<f32>) -> na::Point2<f32> {
+        self.shapes
+            .iter()
+            .fold(point, |point, shape| shape.push_out(point).unwrap_or(point))
+    }
+
+    /// Returns the first obstacle's surface a segment going from `from`
+    /// by `vec` runs into
+    fn hit(
+        &self,
+        topology: WorldTopology,
+        from: na::Point2<f32>,
+        vec: na::Vector2<f32>,
+    ) -> Option<Hit> {
+        self.shapes
+            .iter()
+            .filter_map(|shape| {
+                // On a torus, the obstacle might lie on the other side of
+                // the edge - so we look at it from the nearest side
+                let anchor = shape.anchor();
+                let from = anchor + topology.displacement(anchor, from);
+
+                shape.hit(from, vec)
+            })
+            .min_by(|a, b| a.t.partial_cmp(&b.t).unwrap_or(Ordering::Equal))
+    }
+}
+
+impl Default for ObstacleConfig {
+    fn default() -> Self {
+        Self {
+            shapes: Vec::new(),
+            collision: Collision::default(),
+        }
+    }
+}
+
+/// A single obstacle; coordinates are given in the same unit square
+/// birds and foods live in
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Obstacle {
+    Circle {
+        center: [f32; 2],
+        radius: f32,
+    },
+
+    /// Axis-aligned rectangle
+    Rect {
+        min: [f32; 2],
+        max: [f32; 2],
+    },
+
+    /// Infinitely thin wall, going from one point to another
+    Wall {
+        from: [f32; 2],
+        to: [f32; 2],
+    },
+}
+
+impl Obstacle {
+    fn validate(&self) -> Result<(), ConfigError> {
+        let finite = |point: &[f32; 2]| point.iter().all(|coord| coord.is_finite());
+
+        match self {
+            Self::Circle { center, radius } => ensure(
+                finite(center) && radius.is_finite() && *radius > 0.0,
+                "obstacles.shapes",
+                "circles must have a finite center and a finite, positive radius",
+            ),
+
+            Self::Rect { min, max } => ensure(
+                finite(min) && finite(max) && min[0] < max[0] && min[1] < max[1],
+                "obstacles.shapes",
+                "rectangles must have finite corners, with `min` lying below `max`",
+            ),
+
+            Self::Wall { from, to } => ensure(
+                finite(from) && finite(to) && from != to,
+                "obstacles.shapes",
+                "walls must have finite, distinct ends",
+            ),
+        }
+    }
+
+    /// Returns a point that describes where the obstacle lies
+    fn anchor(&self) -> na::Point2<f32> {
+        match self {
+            Self::Circle { center, .. } => point(center),
+            Self::Rect { min, max } => na::center(&point(min), &point(max)),
+            Self::Wall { from, to } => na::center(&point(from), &point(to)),
+        }
+    }
+
+    /// Returns where a segment going from `from` by `vec` enters this
+    /// obstacle; segments starting inside the obstacle never hit it,
+    /// so that whoever got stuck there can get out.
+    fn hit(&self, from: na::Point2<f32>, vec: na::Vector2<f32>) -> Option<Hit> {
+        let hit = match self {
+            Self::Circle { center, radius } => {
+                let offset = from - point(center);
+
+                let a = vec.dot(&vec);
+                let b = 2.0 * offset.dot(&vec);
+                let c = offset.dot(&offset) - radius * radius;
+
+                if c < 0.0 || a == 0.0 {
+                    return None;
+                }
+
+                let delta = b * b - 4.0 * a * c;
+
+                if delta < 0.0 {
+                    return None;
+                }
+
+                let t = (-b - delta.sqrt()) / (2.0 * a);
+
+                Hit {
+                    t,
+                    normal: (offset + vec * t).normalize(),
+                }
+            }
+
+            Self::Rect { min, max } => {
+                // Slab method - for each axis we check when the segment
+                // enters and leaves the rectangle's span on that axis;
+                // the segment hits the rectangle if those spans overlap
+                let mut enter = (f32::NEG_INFINITY, na::Vector2::zeros());
+                let mut leave = f32::INFINITY;
+
+                for axis in 0..2 {
+                    if vec[axis] == 0.0 {
+                        if from[axis] < min[axis] || from[axis] > max[axis] {
+                            return None;
+                        }
+
+                        continue;
+                    }
+
+                    let t1 = (min[axis] - from[axis]) / vec[axis];
+                    let t2 = (max[axis] - from[axis]) / vec[axis];
+                    let (near, far) = if t1 < t2 { (t1, t2) } else { (t2, t1) };
+
+                    if near > enter.0 {
+                        let mut normal = na::Vector2::zeros();
+                        normal[axis] = -vec[axis].signum();
+
+                        enter = (near, normal);
+                    }
+
+                    leave = leave.min(far);
+                }
+
+                // `enter < 0.0` means we're starting inside
+                if enter.0 < 0.0 || enter.0 > leave {
+                    return None;
+                }
+
+                Hit {
+                    t: enter.0,
+                    normal: enter.1,
+                }
+            }
+
+            Self::Wall { from: a, to: b } => {
+                let wall = point(b) - point(a);
+                let denom = cross(vec, wall);
+
+                if denom == 0.0 {
+                    return None;
+                }
+
+                let offset = point(a) - from;
+                let t = cross(offset, wall) / denom;
+                let u = cross(offset, vec) / denom;
+
+                if !(0.0..=1.0).contains(&u) {
+                    return None;
+                }
+
+                // Wall has two sides - the normal has to face us
+                let normal = na::Vector2::new(-wall.y, wall.x).normalize();
+                let normal = if normal.dot(&vec) > 0.0 {
+                    -normal
+                } else {
+                    normal
+                };
+
+                Hit { t, normal }
+            }
+        };
+
+        if (0.0..=1.0).contains(&hit.t) {
+            Some(hit)
+        } else {
+            None
+        }
+    }
+
+    /// Returns given point moved onto obstacle's surface, if it's lying
+    /// inside of it
+    fn push_out(&self, p: na::Point2<f32>) -> Option<na::Point2<f32>> {
+        match self {
+            Self::Circle { center, radius } => {
+                let offset = p - point(center);
+
+                if offset.norm() >= *radius {
+                    return None;
+                }
+
+                let dir = if offset.norm() > 0.0 {
+                    offset.normalize()
+                } else {
+                    na::Vector2::x()
+                };
+
+                Some(point(center) + dir * (radius + SURFACE_MARGIN))
+            }
+
+            Self::Rect { min, max } => {
+                let inside = (0..2).all(|axis| p[axis] > min[axis] && p[axis] < max[axis]);
+
+                if !inside {
+                    return None;
+                }
+
+                // Pick the nearest edge
+                let exits = [
+                    (p.x - min[0], na::Point2::new(min[0] - SURFACE_MARGIN, p.y)),
+                    (max[0] - p.x, na::Point2::new(max[0] + SURFACE_MARGIN, p.y)),
+                    (p.y - min[1], na::Point2::new(p.x, min[1] - SURFACE_MARGIN)),
+                    (max[1] - p.y, na::Point2::new(p.x, max[1] + SURFACE_MARGIN)),
+                ];
+
+                exits
+                    .iter()
+                    .min_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal))
+                    .map(|exit| exit.1)
+            }
+
+            // Walls are infinitely thin, so nothing can lie inside them
+            Self::Wall { .. } => None,
+        }
+    }
+}
+
+/// What happens when a bird flies into an obstacle
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Collision {
+    /// Bird keeps its rotation and slides along the obstacle
+    Slide,
+
+    /// Bird bounces off the obstacle, as a billiard ball would
+    Bounce,
+}
+
+impl Default for Collision {
+    fn default() -> Self {
+        Self::Slide
+    }
+}
+
+/// Place where a segment runs into an obstacle
+struct Hit {
+    /// Fraction of the segment that's free of the obstacle
+    t: f32,
+
+    /// Obstacle's surface normal, facing the segment
+    normal: na::Vector2<f32>,
+}
+
+fn point([x, y]: &[f32; 2]) -> na::Point2<f32> {
+    na::Point2::new(*x, *y)
+}
+
+fn cross(a: na::Vector2<f32>, b: na::Vector2<f32>) -> f32 {
+    a.x * b.y - a.y * b.x
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+    use std::f32::consts::PI;
+    use test_case::test_case;
+
+    fn config(collision: Collision) -> ObstacleConfig {
+        ObstacleConfig {
+            shapes: vec![
+                Obstacle::Circle {
+                    center: [0.2, 0.5],
+                    radius: 0.05,
+                },
+                Obstacle::Rect {
+                    min: [0.6, 0.4],
+                    max: [0.7, 0.6],
+                },
+                Obstacle::Wall {
+                    from: [0.4, 0.8],
+                    to: [0.6, 0.8],
+                },
+            ],
+            collision,
+        }
+    }
+
+    #[test_case(0.5, 0.5, 0.1, 0.0, false)] // nothing in the way
+    #[test_case(0.5, 0.5, 0.3, 0.0, true)] // rectangle
+    #[test_case(0.5, 0.5, -0.3, 0.0, true)] // circle
+    #[test_case(0.5, 0.7, 0.0, 0.2, true)] // wall
+    #[test_case(0.3, 0.7, 0.0, 0.2, false)] // next to the wall
+    #[test_case(0.95, 0.5, 0.25, 0.0, true)] // circle across the edge
+    fn occlusion(x: f32, y: f32, dx: f32, dy: f32, expected: bool) {
+        let actual = config(Collision::Slide).occludes(
+            WorldTopology::Torus,
+            na::Point2::new(x, y),
+            na::Vector2::new(dx, dy),
+        );
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test_case(Collision::Slide, 0.01, 0.6, 0.51, 0.0)] // slide
+    #[test_case(Collision::Bounce, 0.0, 0.6, 0.5, PI)] // bounce
+    fn hitting_a_rectangle(collision: Collision, dy: f32, x: f32, y: f32, rot: f32) {
+        let mut position = na::Point2::new(0.59, 0.5);
+        let mut rotation = na::Rotation2::new(0.0);
+
+        config(collision).move_through(
+            WorldTopology::Torus,
+            &mut position,
+            &mut rotation,
+            na::Vector2::new(0.02, dy),
+        );
+
+        assert!(position.x < 0.6, "bird got into the rectangle");
+        assert_relative_eq!(position.x, x, epsilon = 1e-3);
+        assert_relative_eq!(position.y, y, epsilon = 1e-3);
+        assert_relative_eq!(rotation.angle().abs(), rot, epsilon = 1e-3);
+    }
+
+    #[test]
+    fn pushes_things_out() {
+        let config = config(Collision::Slide);
+
+        let point = config.push_out(na::Point2::new(0.21, 0.5));
+        assert_relative_eq!(point.x, 0.25, epsilon = 1e-3);
+
+        let point = config.push_out(na::Point2::new(0.69, 0.5));
+        assert_relative_eq!(point.x, 0.7, epsilon = 1e-3);
+    }
+}
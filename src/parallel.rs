@@ -0,0 +1,98 @@
+//! Helpers that spread work across threads when the `parallel` feature is
+//! enabled, and fall back to plain iterators otherwise.
+//!
+//! Both of them process each item on its own, without touching any other
+//! item or any shared RNG - so the results don't depend on how many
+//! threads there are (or on whether there are any at all).
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+/// Returns `f(id, item)` for each item, in order
+#[cfg(feature = "parallel")]
+crate fn map<T, U>(items: &[T], f: impl Fn(usize, &T) -> U + Sync + Send) -> Vec<U>
+where
+    T: Sync,
+    U: Send,
+{
+    items
+        .par_iter()
+        .enumerate()
+        .map(|(id, item)| f(id, item))
+        .collect()
+}
+
+/// Returns `f(id, item)` for each item, in order
+#[cfg(not(feature = "parallel"))]
+crate fn map<T, U>(items: &[T], f: impl Fn(usize, &T) -> U) -> Vec<U> {
+    items
+        .iter()
+        .enumerate()
+        .map(|(id, item)| f(id, item))
+        .collect()
+}
+
+/// Calls `f(item)` for each item
+#[cfg(feature = "parallel")]
+crate fn for_each_mut<T: Send>(items: &mut [T], f: impl Fn(&mut T) + Sync + Send) {
+    items.par_iter_mut().for_each(f);
+}
+
+/// Calls `f(item)` for each item
+#[cfg(not(feature = "parallel"))]
+crate fn for_each_mut<T>(items: &mut [T], f: impl Fn(&mut T)) {
+    items.iter_mut().for_each(f);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{EnergyConfig, Simulation, SimulationConfig};
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    /// Runs a simulation and returns the world it ends up with
+    fn run(config: SimulationConfig) -> String {
+        let mut rng = ChaCha8Rng::from_seed(Default::default());
+        let mut sim = Simulation::from_config(config, &mut rng).unwrap();
+
+        for _ in 0..250 {
+            sim.step(&mut rng);
+        }
+
+        format!("{:?}", sim.world())
+    }
+
+    /// Same as `run()`, but on a thread pool of given size
+    #[cfg(feature = "parallel")]
+    fn run_on(threads: usize, config: SimulationConfig) -> String {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .unwrap()
+            .install(|| run(config))
+    }
+
+    #[test]
+    fn is_deterministic() {
+        let config = |spatial_index| SimulationConfig {
+            animals: 100,
+            generation_length: 100,
+            spatial_index,
+            energy: Some(EnergyConfig::default()),
+            ..Default::default()
+        };
+
+        // Looking foods up through the spatial index shouldn't change
+        // anything either
+        let expected = run(config(false));
+
+        assert_eq!(run(config(true)), expected);
+
+        #[cfg(feature = "parallel")]
+        for &threads in &[1, 4, 16] {
+            for &spatial_index in &[false, true] {
+                assert_eq!(run_on(threads, config(spatial_index)), expected);
+            }
+        }
+    }
+}
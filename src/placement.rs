@@ -0,0 +1,252 @@
+use nalgebra as na;
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use crate::config::ensure;
+use crate::grid::SpatialGrid;
+use crate::{ConfigError, WorldTopology};
+
+/// How many random spots `Placement::PoissonDisk` tries before giving up
+/// and settling for a spot that's too crowded.
+const DART_ATTEMPTS: usize = 30;
+
+/// How things (birds, foods) get laid out on the map.
+///
+/// All of the strategies are deterministic - given an RNG in the same
+/// state, they yield exactly the same layout.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Placement {
+    /// Anywhere, with the same probability; things might overlap
+    Uniform,
+
+    /// Anywhere, but no closer than `min_distance` to each other (and -
+    /// in case of foods scattered at the beginning of a generation - to
+    /// the birds).
+    ///
+    /// Foods that regrow during the simulation keep their distance only
+    /// from other foods - birds move every step anyway, so wherever they
+    /// happen to be when a food regrows doesn't matter much.
+    ///
+    /// We pick places by throwing darts: each thing tries a few random
+    /// spots and takes the first one that's far enough from everything
+    /// placed so far. When the map gets too crowded for that (e.g.
+    /// because you've asked for a thousand foods at least 0.1 apart), we
+    /// give up and take whichever spot came last - so you get a
+    /// best-effort layout instead of an endless loop.
+    PoissonDisk { min_distance: f32 },
+
+    /// On a regular, square-ish grid, each thing being shifted by up to
+    /// `jitter` of the cell's size - so `0.0` yields a perfectly regular
+    /// grid, while `1.0` lets things land anywhere inside their cells.
+    GridJitter { jitter: f32 },
+
+    /// Exactly at given positions; when there are more things than
+    /// positions, the positions get reused in a round-robin fashion.
+    ///
+    /// Things that (re)appear during the simulation - eaten foods,
+    /// newborn birds - land at one of those positions, picked at random.
+    Custom(Vec<[f32; 2]>),
+}
+
+impl Default for Placement {
+    fn default() -> Self {
+        Self::Uniform
+    }
+}
+
+impl Placement {
+    crate fn validate(&self, field: &'static str) -> Result<(), ConfigError> {
+        match self {
+            Self::Uniform => Ok(()),
+
+            Self::PoissonDisk { min_distance } => ensure(
+                min_distance.is_finite() && *min_distance > 0.0,
+                field,
+                "min_distance must be finite and positive",
+            ),
+
+            Self::GridJitter { jitter } => ensure(
+                (0.0..=1.0).contains(jitter),
+                field,
+                "jitter must be within [0, 1]",
+            ),
+
+            Self::Custom(positions) => {
+                ensure(!positions.is_empty(), field, "positions must not be empty")?;
+
+                ensure(
+                    positions
+                        .iter()
+                        .flatten()
+                        .all(|coord| (0.0..=1.0).contains(coord)),
+                    field,
+                    "positions must lie within the unit square",
+                )
+            }
+        }
+    }
+
+    /// Lays `count` things out on the map, keeping them away from the
+    /// `avoid`-ed ones (if the strategy cares about distances at all).
+    crate fn place(
+        &self,
+        count: usize,
+        topology: WorldTopology,
+        avoid: &[na::Point2<f32>],
+        rng: &mut dyn rand::RngCore,
+    ) -> Vec<na::Point2<f32>> {
+        match self {
+            Self::Uniform => (0..count).map(|_| rng.gen()).collect(),
+
+            Self::PoissonDisk { min_distance } => {
+                let mut grid = SpatialGrid::with_capacity(topology, avoid.iter().copied(), count);
+                let mut points = avoid.to_vec();
+
+                for _ in 0..count {
+                    let point = dart(rng, |candidate| {
+                        grid.query(candidate, *min_distance)
+                            .into_iter()
+                            .all(|other| {
+                                topology.distance(candidate, points[other]) >= *min_distance
+                            })
+                    });
+
+                    grid.insert(point);
+                    points.push(point);
+                }
+
+                points.split_off(avoid.len())
+            }
+
+            Self::GridJitter { jitter } => (0..count)
+                .map(|id| grid_point(id, count, *jitter, rng))
+                .collect(),
+
+            Self::Custom(positions) => positions
+                .iter()
+                .cycle()
+                .take(count)
+                .map(|&[x, y]| na::Point2::new(x, y))
+                .collect(),
+        }
+    }
+
+    /// Picks a place for a single thing that (re)appears amongst `count`
+    /// others already laid out.
+    ///
+    /// `is_free(candidate, min_distance)` should tell whether all of the
+    /// other things lie at least `min_distance` away from `candidate`;
+    /// it's only used for `Self::PoissonDisk`.
+    crate fn respawn(
+        &self,
+        count: usize,
+        rng: &mut dyn rand::RngCore,
+        is_free: impl Fn(na::Point2<f32>, f32) -> bool,
+    ) -> na::Point2<f32> {
+        match self {
+            Self::Uniform => rng.gen(),
+
+            Self::PoissonDisk { min_distance } => {
+                dart(rng, |candidate| is_free(candidate, *min_distance))
+            }
+
+            Self::GridJitter { jitter } => {
+                let count = count.max(1);
+                let id = rng.gen_range(0..count);
+
+                grid_point(id, count, *jitter, rng)
+            }
+
+            Self::Custom(positions) => {
+                let [x, y] = *positions
+                    .choose(rng)
+                    .expect("there should be some positions");
+
+                na::Point2::new(x, y)
+            }
+        }
+    }
+}
+
+/// Throws up to `DART_ATTEMPTS` random darts, returning the first one
+/// that lands on a free spot (or the last one, if none does).
+fn dart(rng: &mut dyn rand::RngCore, is_free: impl Fn(na::Point2<f32>) -> bool) -> na::Point2<f32> {
+    let mut candidate = rng.gen();
+
+    for _ in 1..DART_ATTEMPTS {
+        if is_free(candidate) {
+            break;
+        }
+
+        candidate = rng.gen();
+    }
+
+    candidate
+}
+
+/// Returns position of the `id`-th out of `count` points laid out on a
+/// grid (see: `Placement::GridJitter`).
+fn grid_point(
+    id: usize,
+    count: usize,
+    jitter: f32,
+    rng: &mut dyn rand::RngCore,
+) -> na::Point2<f32> {
+    let cols = ((count as f32).sqrt().ceil() as usize).max(1);
+    let rows = ((count as f32 / cols as f32).ceil() as usize).max(1);
+
+    let (col, row) = (id % cols, id / cols);
+
+    let x = (col as f32 + 0.5 + jitter * (rng.gen::<f32>() - 0.5)) / cols as f32;
+    let y = (row as f32 + 0.5 + jitter * (rng.gen::<f32>() - 0.5)) / rows as f32;
+
+    na::Point2::new(x, y)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+    use test_case::test_case;
+
+    #[test_case(Placement::Uniform)]
+    #[test_case(Placement::PoissonDisk { min_distance: 0.05 })]
+    #[test_case(Placement::GridJitter { jitter: 0.5 })]
+    #[test_case(Placement::Custom(vec![[0.25, 0.5], [0.75, 0.5]]))]
+    fn is_deterministic(placement: Placement) {
+        let place = || {
+            let mut rng = ChaCha8Rng::from_seed(Default::default());
+
+            placement.place(100, WorldTopology::Torus, &[], &mut rng)
+        };
+
+        let points = place();
+
+        assert_eq!(points.len(), 100);
+        assert_eq!(points, place());
+
+        for point in points {
+            assert!((0.0..=1.0).contains(&point.x) && (0.0..=1.0).contains(&point.y));
+        }
+    }
+
+    #[test_case(WorldTopology::Torus)]
+    #[test_case(WorldTopology::Bounded)]
+    fn poisson_disk_keeps_things_apart(topology: WorldTopology) {
+        let mut rng = ChaCha8Rng::from_seed(Default::default());
+        let avoid = [na::Point2::new(0.5, 0.5)];
+
+        let points =
+            Placement::PoissonDisk { min_distance: 0.05 }.place(100, topology, &avoid, &mut rng);
+
+        for (id, a) in points.iter().enumerate() {
+            for b in points[..id].iter().chain(&avoid) {
+                let distance = topology.distance(*a, *b);
+
+                assert!(distance >= 0.05, "points lie {} apart", distance);
+            }
+        }
+    }
+}
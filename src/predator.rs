@@ -0,0 +1,209 @@
+use std::f32::consts::{FRAC_PI_2, FRAC_PI_4, PI};
+
+use crate::config::ensure;
+use crate::ConfigError;
+
+/// Second species living in our world: predators that hunt the birds.
+///
+/// Predators are animals too - they've got their own eyes, brains and
+/// speed limits, and they evolve the same way birds do (though in a
+/// separate population, so that both species can co-evolve).
+///
+/// Instead of foods, predators see (and eat) birds; a predator's
+/// `LifetimeStats::food_eaten` tells how many birds it's caught, and
+/// that's what its fitness is based on.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct PredatorConfig {
+    /// How many predators live in the world
+    pub count: usize,
+
+    /// Same as `SimulationConfig::speed_min`, but for predators
+    pub speed_min: f32,
+
+    /// Same as `SimulationConfig::speed_max`, but for predators; by
+    /// default predators are a bit faster than birds...
+    pub speed_max: f32,
+
+    /// Same as `SimulationConfig::speed_accel`, but for predators
+    pub speed_accel: f32,
+
+    /// Same as `SimulationConfig::rotation_accel`, but for predators;
+    /// ... but they turn way slower, so birds do have a chance
+    pub rotation_accel: f32,
+
+    /// Same as `SimulationConfig::fov_range`, but for predators
+    pub fov_range: f32,
+
+    /// Same as `SimulationConfig::fov_angle`, but for predators
+    pub fov_angle: f32,
+
+    /// Same as `SimulationConfig::eye_cells`, but for predators
+    pub eye_cells: usize,
+
+    /// How close a predator has to get to a bird in order to catch it
+    pub catch_range: f32,
+}
+
+impl PredatorConfig {
+    crate fn validate(&self) -> Result<(), ConfigError> {
+        ensure(self.count > 0, "predators.count", "must be positive")?;
+
+        ensure(
+            self.speed_min >= 0.0,
+            "predators.speed_min",
+            "must be non-negative",
+        )?;
+
+        ensure(
+            self.speed_max.is_finite() && self.speed_max >= self.speed_min,
+            "predators.speed_max",
+            "must be finite and not lower than speed_min",
+        )?;
+
+        ensure(
+            self.speed_accel.is_finite() && self.speed_accel >= 0.0,
+            "predators.speed_accel",
+            "must be finite and non-negative",
+        )?;
+
+        ensure(
+            self.rotation_accel.is_finite() && self.rotation_accel >= 0.0,
+            "predators.rotation_accel",
+            "must be finite and non-negative",
+        )?;
+
+        ensure(
+            self.fov_range.is_finite() && self.fov_range > 0.0,
+            "predators.fov_range",
+            "must be finite and positive",
+        )?;
+
+        ensure(
+            self.fov_angle > 0.0 && self.fov_angle <= 2.0 * PI,
+            "predators.fov_angle",
+            "must be within (0, 2*PI]",
+        )?;
+
+        ensure(
+            self.eye_cells > 0,
+            "predators.eye_cells",
+            "must be positive",
+        )?;
+
+        ensure(
+            self.catch_range.is_finite() && self.catch_range >= 0.0,
+            "predators.catch_range",
+            "must be finite and non-negative",
+        )
+    }
+}
+
+impl Default for PredatorConfig {
+    fn default() -> Self {
+        Self {
+            count: 4,
+            speed_min: 0.001,
+            speed_max: 0.006,
+            speed_accel: 0.2,
+            rotation_accel: FRAC_PI_4,
+            fov_range: 0.35,
+            fov_angle: FRAC_PI_2,
+            eye_cells: 9,
+            catch_range: 0.01,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AnimalState, Simulation, SimulationConfig, VisionChannel};
+    use nalgebra as na;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    #[test]
+    fn predators_catch_the_nearest_bird() {
+        let mut rng = ChaCha8Rng::from_seed(Default::default());
+
+        let config = SimulationConfig {
+            animals: 3,
+            vision: vec![VisionChannel::Food, VisionChannel::Predators],
+            predators: Some(PredatorConfig {
+                count: 1,
+                catch_range: 0.3,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let mut sim = Simulation::from_config(config, &mut rng).unwrap();
+
+        sim.world.predators[0].position = na::Point2::new(0.5, 0.5);
+        sim.world.animals[0].position = na::Point2::new(0.7, 0.5);
+        sim.world.animals[1].position = na::Point2::new(0.55, 0.5);
+        sim.world.animals[2].position = na::Point2::new(0.1, 0.1);
+
+        sim.process_collisions(None, &mut rng);
+
+        let states: Vec<_> = sim.world().animals().iter().map(|a| a.state()).collect();
+
+        assert_eq!(
+            states,
+            [AnimalState::Alive, AnimalState::Caught, AnimalState::Alive]
+        );
+    }
+
+    #[test]
+    fn predators_hunt_birds() {
+        let mut rng = ChaCha8Rng::from_seed(Default::default());
+
+        let config = SimulationConfig {
+            animals: 40,
+            foods: 50,
+            eat_range: 0.05,
+            generation_length: 200,
+            vision: vec![VisionChannel::Food, VisionChannel::Predators],
+            predators: Some(PredatorConfig {
+                count: 10,
+                catch_range: 0.05,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let mut sim = Simulation::from_config(config, &mut rng).unwrap();
+
+        for _ in 0..200 {
+            sim.step(&mut rng);
+        }
+
+        let caught = sim
+            .world()
+            .animals()
+            .iter()
+            .filter(|bird| bird.state() == AnimalState::Caught)
+            .count();
+
+        let hunts: Vec<_> = sim
+            .world()
+            .predators()
+            .iter()
+            .map(|predator| predator.satiation())
+            .collect();
+
+        assert!(caught > 0);
+        assert_eq!(caught, hunts.iter().sum::<usize>());
+
+        // Generation ends, both species evolve
+        let outcome = sim.step(&mut rng).unwrap();
+        let stats = outcome.statistics().unwrap().predators().unwrap();
+
+        assert!(stats.max_fitness() >= *hunts.iter().max().unwrap() as f32);
+
+        assert_eq!(sim.world().predators().len(), 10);
+        assert!(sim.world().animals().iter().all(|bird| bird.is_alive()));
+    }
+}
@@ -0,0 +1,530 @@
+use rand::RngCore;
+use std::{error, fmt};
+
+use crate::config::ensure;
+use crate::{ConfigError, Simulation, Snapshot, SnapshotError, StepOutcome, World};
+
+/// Version of the `Recording`'s layout; bump it whenever the layout
+/// changes, so that older logs get rejected instead of misinterpreted.
+const RECORDING_VERSION: u32 = 1;
+
+/// Log of a simulation's run, allowing to replay it step by step (see:
+/// `Replayer`).
+///
+/// Our simulation is deterministic - the only thing that brings any
+/// randomness into it is the RNG - so instead of storing each step's
+/// outcome (food respawns, births, brains' outputs and whatnot), we just
+/// store the bytes each step has drawn from the RNG; feeding those very
+/// same bytes back into the simulation reproduces the step exactly.
+///
+/// Replaying a long run from its very beginning would be slow, though,
+/// so every few steps we also capture a keyframe - a full snapshot of
+/// the simulation - which the replayer can seek to, simulating only the
+/// remaining few steps.
+///
+/// Note that custom fitness functions (see: `Simulation::set_fitness()`)
+/// are not a part of keyframes - replaying a run that used one diverges
+/// as soon as the first generation ends.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Recording {
+    version: u32,
+    keyframe_interval: usize,
+
+    /// `keyframes[i]` is the state of the simulation after
+    /// `i * keyframe_interval` steps
+    keyframes: Vec<Snapshot>,
+
+    steps: Vec<StepRecord>,
+}
+
+/// Everything we need to know about a single step
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct StepRecord {
+    /// Bytes the step has drawn from the RNG
+    draws: Vec<u8>,
+
+    /// Fingerprint of the world right after the step; it allows the
+    /// replayer to tell when it's gone astray (e.g. because the log has
+    /// been recorded by a different version of the simulation)
+    digest: u64,
+}
+
+impl Recording {
+    /// Number of steps recorded
+    pub fn len(&self) -> usize {
+        self.steps.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    /// Encodes this recording into a compact binary form
+    #[cfg(feature = "serde")]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        bincode::serialize(self).expect("recordings should always be serializable")
+    }
+
+    /// Decodes recording previously encoded via `.to_bytes()`
+    #[cfg(feature = "serde")]
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ReplayError> {
+        let recording: Self =
+            bincode::deserialize(bytes).map_err(|err| ReplayError::Malformed {
+                reason: err.to_string(),
+            })?;
+
+        if recording.version != RECORDING_VERSION {
+            return Err(ReplayError::UnsupportedVersion {
+                found: recording.version,
+            });
+        }
+
+        Ok(recording)
+    }
+}
+
+/// Records steps of a simulation (see: `Simulation::start_recording()`)
+crate struct Recorder {
+    recording: Recording,
+}
+
+impl Recorder {
+    crate fn new(
+        simulation: &Simulation,
+        keyframe_interval: usize,
+    ) -> Result<Self, ConfigError> {
+        ensure(
+            keyframe_interval > 0,
+            "keyframe_interval",
+            "must be positive",
+        )?;
+
+        Ok(Self {
+            recording: Recording {
+                version: RECORDING_VERSION,
+                keyframe_interval,
+                keyframes: vec![simulation.snapshot()],
+                steps: Vec::new(),
+            },
+        })
+    }
+
+    crate fn record(
+        &mut self,
+        simulation: &mut Simulation,
+        rng: &mut dyn rand::RngCore,
+    ) -> Option<StepOutcome> {
+        let mut rng = RecordingRng {
+            rng,
+            draws: Vec::new(),
+        };
+
+        let outcome = simulation.step(&mut rng);
+
+        self.recording.steps.push(StepRecord {
+            draws: rng.draws,
+            digest: digest(simulation.world()),
+        });
+
+        // Keyframes lie `keyframe_interval` steps apart, starting with
+        // the very first one
+        let next_keyframe = self.recording.keyframes.len() * self.recording.keyframe_interval;
+
+        if self.recording.steps.len() == next_keyframe {
+            self.recording.keyframes.push(simulation.snapshot());
+        }
+
+        outcome
+    }
+
+    crate fn finish(self) -> Recording {
+        self.recording
+    }
+}
+
+/// Plays back a `Recording`, allowing to inspect the world at any of the
+/// recorded steps.
+pub struct Replayer {
+    recording: Recording,
+    simulation: Simulation,
+    step: usize,
+}
+
+impl Replayer {
+    pub fn new(recording: Recording) -> Result<Self, ReplayError> {
+        let simulation = restore(&recording, 0)?;
+
+        Ok(Self {
+            recording,
+            simulation,
+            step: 0,
+        })
+    }
+
+    /// Number of steps replayed so far, i.e. the step `.world()`
+    /// corresponds to
+    pub fn step(&self) -> usize {
+        self.step
+    }
+
+    pub fn recording(&self) -> &Recording {
+        &self.recording
+    }
+
+    pub fn simulation(&self) -> &Simulation {
+        &self.simulation
+    }
+
+    pub fn world(&self) -> &World {
+        self.simulation.world()
+    }
+
+    /// Replays the next step
+    pub fn forward(&mut self) -> Result<Option<StepOutcome>, ReplayError> {
+        let record = self
+            .recording
+            .steps
+            .get(self.step)
+            .ok_or(ReplayError::OutOfRange {
+                step: self.step + 1,
+                len: self.recording.len(),
+            })?;
+
+        let mut rng = ReplayingRng {
+            draws: &record.draws,
+            overrun: false,
+        };
+
+        let outcome = self.simulation.step(&mut rng);
+
+        if rng.overrun || !rng.draws.is_empty() || digest(self.world()) != record.digest {
+            return Err(ReplayError::Diverged { step: self.step });
+        }
+
+        self.step += 1;
+
+        Ok(outcome)
+    }
+
+    /// Goes back by one step.
+    ///
+    /// Since steps cannot be undone, this restores the nearest keyframe
+    /// and replays the steps following it - so it's a bit slower than
+    /// going forward.
+    pub fn back(&mut self) -> Result<(), ReplayError> {
+        match self.step.checked_sub(1) {
+            Some(step) => self.seek(step),
+
+            None => Err(ReplayError::OutOfRange {
+                step: 0,
+                len: self.recording.len(),
+            }),
+        }
+    }
+
+    /// Jumps to given step
+    pub fn seek(&mut self, step: usize) -> Result<(), ReplayError> {
+        if step > self.recording.len() {
+            return Err(ReplayError::OutOfRange {
+                step,
+                len: self.recording.len(),
+            });
+        }
+
+        let keyframe = step / self.recording.keyframe_interval;
+        let keyframe_step = keyframe * self.recording.keyframe_interval;
+
+        // If we're already somewhere between the keyframe and the step,
+        // it's faster to just keep going
+        if !(keyframe_step..=step).contains(&self.step) {
+            self.simulation = restore(&self.recording, keyframe)?;
+            self.step = keyframe_step;
+        }
+
+        while self.step < step {
+            self.forward()?;
+        }
+
+        Ok(())
+    }
+}
+
+fn restore(recording: &Recording, keyframe: usize) -> Result<Simulation, ReplayError> {
+    let snapshot = recording
+        .keyframes
+        .get(keyframe)
+        .cloned()
+        .ok_or(ReplayError::MissingKeyframe { keyframe })?;
+
+    Simulation::restore(snapshot).map_err(ReplayError::InvalidKeyframe)
+}
+
+/// Returns a fingerprint of the world (see: FNV-1a); it doesn't have to
+/// be cryptographically strong, it just has to change when the world does
+fn digest(world: &World) -> u64 {
+    let animals = world.animals.iter().chain(&world.predators);
+
+    let values = animals
+        .flat_map(|animal| {
+            [
+                animal.position.x,
+                animal.position.y,
+                animal.rotation.angle(),
+                animal.speed,
+                animal.energy,
+            ]
+        })
+        .chain(
+            world
+                .foods
+                .iter()
+                .flat_map(|food| [food.position.x, food.position.y]),
+        );
+
+    values
+        .flat_map(|value| value.to_bits().to_le_bytes())
+        .fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+        })
+}
+
+/// RNG that remembers everything it passes on from the wrapped RNG
+struct RecordingRng<'a> {
+    rng: &'a mut dyn rand::RngCore,
+    draws: Vec<u8>,
+}
+
+impl RngCore for RecordingRng<'_> {
+    fn next_u32(&mut self) -> u32 {
+        let value = self.rng.next_u32();
+        self.draws.extend_from_slice(&value.to_le_bytes());
+        value
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let value = self.rng.next_u64();
+        self.draws.extend_from_slice(&value.to_le_bytes());
+        value
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.rng.fill_bytes(dest);
+        self.draws.extend_from_slice(dest);
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+/// RNG that plays back bytes recorded by `RecordingRng`
+struct ReplayingRng<'a> {
+    draws: &'a [u8],
+
+    /// Whether the simulation has asked for more bytes than recorded -
+    /// i.e. whether the replay has diverged
+    overrun: bool,
+}
+
+impl ReplayingRng<'_> {
+    fn take<const N: usize>(&mut self) -> [u8; N] {
+        let mut bytes = [0; N];
+        self.fill_bytes(&mut bytes);
+        bytes
+    }
+}
+
+impl RngCore for ReplayingRng<'_> {
+    fn next_u32(&mut self) -> u32 {
+        u32::from_le_bytes(self.take())
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        u64::from_le_bytes(self.take())
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        if dest.len() > self.draws.len() {
+            self.overrun = true;
+            self.draws = &[];
+            return;
+        }
+
+        let (bytes, rest) = self.draws.split_at(dest.len());
+
+        dest.copy_from_slice(bytes);
+        self.draws = rest;
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+/// Describes why given `Recording` couldn't be replayed
+#[derive(Clone, Debug, PartialEq)]
+pub enum ReplayError {
+    /// Recording has been created by an incompatible version of this
+    /// crate
+    UnsupportedVersion { found: u32 },
+
+    /// Recording's bytes couldn't be decoded (see:
+    /// `Recording::from_bytes()`)
+    Malformed { reason: String },
+
+    /// Recording doesn't contain the keyframe we need; since recordings
+    /// are created only by `Simulation`, this means it's been tampered
+    /// with
+    MissingKeyframe { keyframe: usize },
+
+    /// One of the keyframes couldn't be restored
+    InvalidKeyframe(SnapshotError),
+
+    /// Asked for a step that's not a part of the recording
+    OutOfRange { step: usize, len: usize },
+
+    /// Replaying given step yielded a different world than the one
+    /// that's been recorded
+    Diverged { step: usize },
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnsupportedVersion { found } => write!(
+                f,
+                "unsupported recording version: {} (expected {})",
+                found, RECORDING_VERSION
+            ),
+
+            Self::Malformed { reason } => write!(f, "malformed recording: {}", reason),
+
+            Self::MissingKeyframe { keyframe } => {
+                write!(f, "recording lacks keyframe #{}", keyframe)
+            }
+
+            Self::InvalidKeyframe(err) => write!(f, "recording contains invalid keyframe: {}", err),
+
+            Self::OutOfRange { step, len } => write!(
+                f,
+                "step {} is out of range (recording contains {} steps)",
+                step, len
+            ),
+
+            Self::Diverged { step } => write!(f, "replay diverged at step {}", step),
+        }
+    }
+}
+
+impl error::Error for ReplayError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::InvalidKeyframe(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SimulationConfig;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    fn record() -> (Recording, Vec<Snapshot>) {
+        let mut rng = ChaCha8Rng::from_seed(Default::default());
+
+        let config = SimulationConfig {
+            animals: 10,
+            foods: 50,
+            eat_range: 0.05,
+            generation_length: 20,
+            ..Default::default()
+        };
+
+        let mut sim = Simulation::from_config(config, &mut rng).unwrap();
+        let mut snapshots = vec![sim.snapshot()];
+
+        sim.start_recording(10).unwrap();
+
+        for _ in 0..45 {
+            sim.step(&mut rng);
+            snapshots.push(sim.snapshot());
+        }
+
+        (sim.stop_recording().unwrap(), snapshots)
+    }
+
+    #[test]
+    fn replays_every_step() {
+        let (recording, snapshots) = record();
+        let mut replayer = Replayer::new(recording).unwrap();
+
+        assert_eq!(replayer.recording().len(), 45);
+
+        // Forward, backward and all over the place
+        for &step in &[0, 1, 33, 45, 44, 12, 5, 21, 22, 20] {
+            replayer.seek(step).unwrap();
+
+            assert_eq!(replayer.step(), step);
+            assert_eq!(replayer.simulation().snapshot(), snapshots[step]);
+        }
+
+        replayer.back().unwrap();
+        assert_eq!(replayer.simulation().snapshot(), snapshots[19]);
+
+        assert_eq!(
+            replayer.seek(46),
+            Err(ReplayError::OutOfRange { step: 46, len: 45 })
+        );
+    }
+
+    #[test]
+    fn rejects_zero_keyframe_interval() {
+        let mut rng = ChaCha8Rng::from_seed(Default::default());
+        let mut sim = Simulation::random(&mut rng);
+
+        assert_eq!(
+            sim.start_recording(0).unwrap_err().field(),
+            "keyframe_interval"
+        );
+
+        assert!(sim.stop_recording().is_none());
+    }
+
+    #[test]
+    fn detects_divergence() {
+        let (mut recording, _) = record();
+
+        // Pretend that the first step that's drawn anything has drawn
+        // something else
+        let step = recording
+            .steps
+            .iter()
+            .position(|step| !step.draws.is_empty())
+            .unwrap();
+
+        for byte in &mut recording.steps[step].draws {
+            *byte ^= 0xff;
+        }
+
+        let mut replayer = Replayer::new(recording).unwrap();
+
+        assert_eq!(replayer.seek(step + 1), Err(ReplayError::Diverged { step }));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn survives_serialization() {
+        let (recording, _) = record();
+        let bytes = recording.to_bytes();
+
+        assert_eq!(Recording::from_bytes(&bytes).unwrap(), recording);
+        assert!(Recording::from_bytes(&bytes[..10]).is_err());
+    }
+}
@@ -0,0 +1,418 @@
+use std::cmp::Ordering;
+use std::time::{Duration, Instant};
+
+use crate::neuroevolution::{NeuralGenes, StructuralGenes};
+use crate::{Animal, FitnessFunction, SimulationConfig, Statistics};
+
+/// Detailed summary of a single generation, as returned from
+/// `Simulation::train_with_report()`.
+///
+/// Contrary to `Statistics`, it's got a flat structure, so that it can be
+/// easily dumped into a CSV file (see: `.to_csv()`) or - with the `serde`
+/// feature enabled - into JSON.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GenerationReport {
+    crate generation: usize,
+    crate min_fitness: f32,
+    crate max_fitness: f32,
+    crate avg_fitness: f32,
+    crate median_fitness: f32,
+    crate fitness_stddev: f32,
+    crate best_fitness_ever: f32,
+    crate total_food_eaten: usize,
+    crate avg_distance_travelled: f32,
+    crate avg_speed: f32,
+    crate starving_fraction: f32,
+    crate diversity: f32,
+    crate wall_time_secs: Option<f64>,
+}
+
+impl GenerationReport {
+    /// Names of the columns returned from `.to_csv()`
+    pub const CSV_HEADER: &'static str = "generation,min_fitness,max_fitness,avg_fitness,\
+                                          median_fitness,fitness_stddev,best_fitness_ever,\
+                                          total_food_eaten,avg_distance_travelled,avg_speed,\
+                                          starving_fraction,diversity,wall_time_secs";
+
+    /// Number of the generation this report describes, starting from zero
+    pub fn generation(&self) -> usize {
+        self.generation
+    }
+
+    pub fn min_fitness(&self) -> f32 {
+        self.min_fitness
+    }
+
+    pub fn max_fitness(&self) -> f32 {
+        self.max_fitness
+    }
+
+    pub fn avg_fitness(&self) -> f32 {
+        self.avg_fitness
+    }
+
+    pub fn median_fitness(&self) -> f32 {
+        self.median_fitness
+    }
+
+    /// Standard deviation of the fitness
+    pub fn fitness_stddev(&self) -> f32 {
+        self.fitness_stddev
+    }
+
+    /// Same as `Statistics::best_fitness_ever()`
+    pub fn best_fitness_ever(&self) -> f32 {
+        self.best_fitness_ever
+    }
+
+    /// Number of foods eaten by all of the birds together
+    pub fn total_food_eaten(&self) -> usize {
+        self.total_food_eaten
+    }
+
+    /// How far, on average, a bird has flown
+    pub fn avg_distance_travelled(&self) -> f32 {
+        self.avg_distance_travelled
+    }
+
+    /// How fast, on average, a bird has flown (i.e. the distance it's
+    /// travelled divided by the number of steps it's lived for)
+    pub fn avg_speed(&self) -> f32 {
+        self.avg_speed
+    }
+
+    /// Fraction of birds that haven't eaten anything at all - from `0.0`
+    /// (everybody's eaten something) to `1.0` (nobody has)
+    pub fn starving_fraction(&self) -> f32 {
+        self.starving_fraction
+    }
+
+    /// Mean distance between brains of each pair of birds, treating
+    /// brains' weights as points in space; the lower it gets, the more
+    /// alike the birds think.
+    ///
+    /// (brains of different shapes, see: `SimulationConfig::neuroevolution`,
+    /// get lined up neuron by neuron first, and are then compared on the
+    /// weights they share.)
+    pub fn diversity(&self) -> f32 {
+        self.diversity
+    }
+
+    /// How long it took to simulate this generation; it's `None` on
+    /// `wasm32`, where there's no clock to measure it with.
+    pub fn wall_time(&self) -> Option<Duration> {
+        self.wall_time_secs.map(Duration::from_secs_f64)
+    }
+
+    /// Returns this report as a single line of CSV (see: `CSV_HEADER`)
+    pub fn to_csv(&self) -> String {
+        format!(
+            "{},{},{},{},{},{},{},{},{},{},{},{},{}",
+            self.generation,
+            self.min_fitness,
+            self.max_fitness,
+            self.avg_fitness,
+            self.median_fitness,
+            self.fitness_stddev,
+            self.best_fitness_ever,
+            self.total_food_eaten,
+            self.avg_distance_travelled,
+            self.avg_speed,
+            self.starving_fraction,
+            self.diversity,
+            self.wall_time_secs
+                .map(|secs| secs.to_string())
+                .unwrap_or_default(),
+        )
+    }
+}
+
+/// Everything there's to know about the birds of a generation that's
+/// about to end (see: `Simulation::train_with_report()`)
+crate struct Summary {
+    fitness: Vec<f32>,
+    total_food_eaten: usize,
+    avg_distance_travelled: f32,
+    avg_speed: f32,
+    starving_fraction: f32,
+    diversity: f32,
+}
+
+impl Summary {
+    crate fn new(
+        config: &SimulationConfig,
+        fitness: &dyn FitnessFunction,
+        animals: &[Animal],
+    ) -> Self {
+        let count = animals.len().max(1) as f32;
+        let stats = animals.iter().map(|animal| &animal.stats);
+
+        let speed = |distance: f32, lifetime: usize| {
+            if lifetime > 0 {
+                distance / lifetime as f32
+            } else {
+                0.0
+            }
+        };
+
+        let diversity = match StructuralGenes::of_birds(config) {
+            Some(structure) => {
+                let brains: Vec<_> = animals
+                    .iter()
+                    .map(|animal| structure.brain(&animal.as_chromosome()))
+                    .collect();
+
+                diversity(&brains, NeuralGenes::distance)
+            }
+
+            None => {
+                let genes: Vec<_> = animals
+                    .iter()
+                    .map(|animal| animal.genome().into_genes())
+                    .collect();
+
+                diversity(&genes, |a, b| distance(a, b))
+            }
+        };
+
+        Self {
+            // Same as in `AnimalIndividual::from_animal()`, so that the
+            // numbers match the ones in `Statistics`
+            fitness: stats
+                .clone()
+                .map(|stats| fitness.fitness(stats).max(0.0))
+                .collect(),
+            total_food_eaten: stats.clone().map(|stats| stats.food_eaten).sum(),
+            avg_distance_travelled: stats
+                .clone()
+                .map(|stats| stats.distance_travelled)
+                .sum::<f32>()
+                / count,
+            avg_speed: stats
+                .clone()
+                .map(|stats| speed(stats.distance_travelled, stats.lifetime))
+                .sum::<f32>()
+                / count,
+            starving_fraction: stats.filter(|stats| stats.food_eaten == 0).count() as f32 / count,
+            diversity,
+        }
+    }
+
+    crate fn into_report(
+        mut self,
+        stats: Statistics,
+        wall_time: Option<Duration>,
+    ) -> GenerationReport {
+        let fitness_stddev = stddev(&self.fitness);
+
+        GenerationReport {
+            generation: stats.generation(),
+            min_fitness: stats.min_fitness(),
+            max_fitness: stats.max_fitness(),
+            avg_fitness: stats.avg_fitness(),
+            median_fitness: median(&mut self.fitness),
+            fitness_stddev,
+            best_fitness_ever: stats.best_fitness_ever(),
+            total_food_eaten: self.total_food_eaten,
+            avg_distance_travelled: self.avg_distance_travelled,
+            avg_speed: self.avg_speed,
+            starving_fraction: self.starving_fraction,
+            diversity: self.diversity,
+            wall_time_secs: wall_time.map(|time| time.as_secs_f64()),
+        }
+    }
+}
+
+/// Measures how long something takes, if there's a clock to measure it
+/// with - `std::time::Instant` panics on `wasm32-unknown-unknown`.
+crate struct Timer(Option<Instant>);
+
+impl Timer {
+    crate fn start() -> Self {
+        if cfg!(target_arch = "wasm32") {
+            Self(None)
+        } else {
+            Self(Some(Instant::now()))
+        }
+    }
+
+    crate fn elapsed(&self) -> Option<Duration> {
+        self.0.map(|start| start.elapsed())
+    }
+}
+
+fn median(values: &mut [f32]) -> f32 {
+    if values.is_empty() {
+        return 0.0;
+    }
+
+    values.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+
+    // For odd lengths both of these point at the same, middle value
+    let lower = values[(values.len() - 1) / 2];
+    let upper = values[values.len() / 2];
+
+    (lower + upper) / 2.0
+}
+
+fn stddev(values: &[f32]) -> f32 {
+    if values.is_empty() {
+        return 0.0;
+    }
+
+    let mean = values.iter().sum::<f32>() / values.len() as f32;
+
+    let variance = values
+        .iter()
+        .map(|value| (value - mean).powi(2))
+        .sum::<f32>()
+        / values.len() as f32;
+
+    variance.sqrt()
+}
+
+/// Returns mean distance between each pair of given points
+fn diversity<T>(points: &[T], distance: impl Fn(&T, &T) -> f32) -> f32 {
+    let mut total = 0.0;
+    let mut pairs = 0;
+
+    for (id, a) in points.iter().enumerate() {
+        for b in &points[id + 1..] {
+            total += distance(a, b);
+            pairs += 1;
+        }
+    }
+
+    if pairs > 0 {
+        total / pairs as f32
+    } else {
+        0.0
+    }
+}
+
+/// Returns Euclidean distance between two points of the same dimension
+fn distance(a: &[f32], b: &[f32]) -> f32 {
+    a.iter()
+        .zip(b)
+        .map(|(a, b)| (a - b).powi(2))
+        .sum::<f32>()
+        .sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Fitness, NeuroevolutionConfig, Simulation, SimulationConfig, WeightedSum};
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+    use std::collections::HashSet;
+    use test_case::test_case;
+
+    #[test_case(vec![], 0.0, 0.0)]
+    #[test_case(vec![3.0], 3.0, 0.0)]
+    #[test_case(vec![4.0, 1.0, 2.0, 1.0], 1.5, 1.2247449)]
+    #[test_case(vec![5.0, 1.0, 3.0], 3.0, 1.6329932)]
+    fn fitness(mut values: Vec<f32>, expected_median: f32, expected_stddev: f32) {
+        approx::assert_relative_eq!(stddev(&values), expected_stddev);
+        approx::assert_relative_eq!(median(&mut values), expected_median);
+    }
+
+    #[test_case(Fitness::Satiation)]
+    #[test_case(Fitness::WeightedSum(WeightedSum {
+        food_eaten: 1.0,
+        distance_travelled: -0.5,
+        ..Default::default()
+    }))] // often negative, i.e. clamped to zero
+    fn reports_the_generation(fitness: Fitness) {
+        let config = SimulationConfig {
+            animals: 10,
+            generation_length: 100,
+            fitness,
+            ..Default::default()
+        };
+
+        let mut rng = ChaCha8Rng::from_seed(Default::default());
+        let mut sim = Simulation::from_config(config.clone(), &mut rng).unwrap();
+        let mut reports = Vec::new();
+
+        for _ in 0..3 {
+            reports.push(sim.train_with_report(&mut rng));
+        }
+
+        // Reporting shouldn't affect the simulation itself
+        let mut rng = ChaCha8Rng::from_seed(Default::default());
+        let mut twin = Simulation::from_config(config.clone(), &mut rng).unwrap();
+
+        for report in &reports {
+            let stats = twin.train(&mut rng);
+
+            assert_eq!(report.generation(), stats.generation());
+            assert_eq!(report.min_fitness(), stats.min_fitness());
+            assert_eq!(report.max_fitness(), stats.max_fitness());
+            assert_eq!(report.avg_fitness(), stats.avg_fitness());
+        }
+
+        // Everything else should describe the very same birds that the
+        // statistics describe - i.e. the ones that have just been replaced
+        let report = reports.last().unwrap();
+
+        let mut fitness: Vec<_> = sim
+            .retired
+            .iter()
+            .map(|bird| config.fitness.fitness(&bird.stats).max(0.0))
+            .collect();
+
+        let total_food_eaten: usize = sim.retired.iter().map(|bird| bird.stats.food_eaten).sum();
+        let avg_fitness = fitness.iter().sum::<f32>() / 10.0;
+
+        approx::assert_relative_eq!(report.avg_fitness(), avg_fitness, epsilon = 1e-5);
+        assert_eq!(report.fitness_stddev(), stddev(&fitness));
+        assert_eq!(report.median_fitness(), median(&mut fitness));
+        assert_eq!(report.total_food_eaten(), total_food_eaten);
+
+        assert!(report.min_fitness() >= 0.0);
+        assert!(report.min_fitness() <= report.median_fitness());
+        assert!(report.median_fitness() <= report.max_fitness());
+        assert!(report.diversity() > 0.0);
+        assert!(report.wall_time().is_some());
+
+        assert_eq!(
+            report.to_csv().split(',').count(),
+            GenerationReport::CSV_HEADER.split(',').count()
+        );
+    }
+
+    #[test]
+    fn measures_diversity_of_differently_shaped_brains() {
+        let config = SimulationConfig {
+            animals: 10,
+            generation_length: 50,
+            neuroevolution: Some(NeuroevolutionConfig {
+                add_neuron_chance: 0.5,
+                remove_neuron_chance: 0.5,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let mut rng = ChaCha8Rng::from_seed(Default::default());
+        let mut sim = Simulation::from_config(config, &mut rng).unwrap();
+
+        sim.train(&mut rng);
+
+        let shapes: HashSet<_> = sim
+            .world()
+            .animals()
+            .iter()
+            .map(|animal| animal.genome().hidden())
+            .collect();
+
+        assert!(shapes.len() > 1);
+
+        let diversity = sim.train_with_report(&mut rng).diversity();
+
+        assert!(diversity.is_finite());
+        assert!(diversity > 0.0);
+    }
+}
@@ -0,0 +1,502 @@
+use nalgebra as na;
+use std::{error, fmt};
+
+use crate::animal::ChromosomeLayout;
+use crate::config::ensure;
+use crate::evolution::EvolutionState;
+use crate::food::FoodPatch;
+use crate::id::Counter;
+use crate::{
+    Animal, AnimalId, AnimalState, ConfigError, Food, FoodId, Genealogy, LifetimeStats, Simulation,
+    SimulationConfig, Species, World,
+};
+
+/// Version of the snapshot format; bumped each time a snapshot saved by
+/// the previous version of this crate wouldn't restore correctly.
+crate const SNAPSHOT_VERSION: u32 = 1;
+
+/// Entire state of a simulation, frozen in time.
+///
+/// Restoring a snapshot and stepping it with a PRNG in the same state
+/// as the original one yields exactly the same results - that's why we
+/// keep rotations as their raw sine & cosine (instead of an angle that'd
+/// have to be converted back and forth, losing precision).
+///
+/// PRNG itself is not a part of the snapshot, since it's provided from
+/// the outside.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Snapshot {
+    version: u32,
+    config: SimulationConfig,
+    age: usize,
+    evolution: EvolutionState,
+    predator_evolution: EvolutionState,
+    animals: Vec<AnimalSnapshot>,
+    predators: Vec<AnimalSnapshot>,
+    foods: Vec<FoodSnapshot>,
+    patches: Vec<PatchSnapshot>,
+    clock: usize,
+    genealogy: Genealogy,
+    food_ids: Counter,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct AnimalSnapshot {
+    id: AnimalId,
+    position: [f32; 2],
+    rotation: [f32; 2],
+    speed: f32,
+    stats: LifetimeStats,
+    energy: f32,
+    state: AnimalState,
+
+    /// Entire chromosome - body genes followed by the brain (see:
+    /// `ChromosomeLayout`)
+    genes: Vec<f32>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct FoodSnapshot {
+    id: FoodId,
+    position: [f32; 2],
+    nutrition: f32,
+    regrowth: usize,
+    dormant: bool,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct PatchSnapshot {
+    center: [f32; 2],
+    heading: f32,
+}
+
+impl Snapshot {
+    crate fn new(sim: &Simulation) -> Self {
+        Self {
+            version: SNAPSHOT_VERSION,
+            config: sim.config.clone(),
+            age: sim.age,
+            evolution: sim.evolution.clone(),
+            predator_evolution: sim.predator_evolution.clone(),
+            animals: sim.world.animals.iter().map(AnimalSnapshot::new).collect(),
+            predators: sim
+                .world
+                .predators
+                .iter()
+                .map(AnimalSnapshot::new)
+                .collect(),
+            foods: sim.world.foods.iter().map(FoodSnapshot::new).collect(),
+            patches: sim.world.patches.iter().map(PatchSnapshot::new).collect(),
+            clock: sim.world.clock,
+            genealogy: sim.world.genealogy.clone(),
+            food_ids: sim.world.food_ids.clone(),
+        }
+    }
+
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    pub fn config(&self) -> &SimulationConfig {
+        &self.config
+    }
+
+    /// Number of steps taken in the current generation
+    pub fn age(&self) -> usize {
+        self.age
+    }
+
+    crate fn restore(self) -> Result<Simulation, SnapshotError> {
+        if self.version != SNAPSHOT_VERSION {
+            return Err(SnapshotError::UnsupportedVersion {
+                found: self.version,
+            });
+        }
+
+        self.config
+            .validate()
+            .map_err(SnapshotError::InvalidConfig)?;
+
+        let config = self.config;
+
+        let animals = self
+            .animals
+            .into_iter()
+            .enumerate()
+            .map(|(id, animal)| {
+                animal.restore(Species::Bird, &config, |expected, actual| match expected {
+                    Some(expected) => SnapshotError::InvalidBrain {
+                        animal: id,
+                        expected,
+                        actual,
+                    },
+
+                    None => SnapshotError::InvalidBrainShape { animal: id },
+                })
+            })
+            .collect::<Result<_, _>>()?;
+
+        ensure(
+            config.predators.is_some() || self.predators.is_empty(),
+            "predators",
+            "snapshot contains predators, but they are disabled",
+        )
+        .map_err(SnapshotError::InvalidConfig)?;
+
+        let predators = self
+            .predators
+            .into_iter()
+            .enumerate()
+            .map(|(id, predator)| {
+                predator.restore(Species::Predator, &config, |expected, actual| {
+                    SnapshotError::InvalidPredatorBrain {
+                        predator: id,
+                        expected: expected.expect("predators' brains have fixed shape"),
+                        actual,
+                    }
+                })
+            })
+            .collect::<Result<_, _>>()?;
+
+        let foods = self.foods.into_iter().map(FoodSnapshot::restore).collect();
+
+        let patches = self
+            .patches
+            .into_iter()
+            .map(PatchSnapshot::restore)
+            .collect();
+
+        let world = World {
+            animals,
+            predators,
+            foods,
+            patches,
+            clock: self.clock,
+            genealogy: self.genealogy,
+            food_ids: self.food_ids,
+        };
+
+        Ok(Simulation {
+            age: self.age,
+            evolution: self.evolution,
+            predator_evolution: self.predator_evolution,
+            ..Simulation::new(config, world)
+        })
+    }
+}
+
+impl AnimalSnapshot {
+    fn new(animal: &Animal) -> Self {
+        let rotation = animal.rotation.matrix();
+
+        Self {
+            id: animal.id,
+            position: [animal.position.x, animal.position.y],
+            rotation: [rotation[(0, 0)], rotation[(1, 0)]],
+            speed: animal.speed,
+            stats: animal.stats.clone(),
+            energy: animal.energy,
+            state: animal.state,
+            genes: animal.as_chromosome().into_iter().collect(),
+        }
+    }
+
+    fn restore(
+        self,
+        species: Species,
+        config: &SimulationConfig,
+        invalid_brain: impl FnOnce(Option<usize>, usize) -> SnapshotError,
+    ) -> Result<Animal, SnapshotError> {
+        let expected = ChromosomeLayout::new(species, config).expected_len(&self.genes);
+
+        if expected != Some(self.genes.len()) {
+            return Err(invalid_brain(expected, self.genes.len()));
+        }
+
+        let (body, eye, brain) = Animal::decode(species, self.genes.into_iter().collect(), config);
+
+        let [cos, sin] = self.rotation;
+
+        Ok(Animal {
+            id: self.id,
+            position: self.position.into(),
+            rotation: na::Rotation2::from_matrix_unchecked(na::Matrix2::new(cos, -sin, sin, cos)),
+            speed: self.speed,
+            body,
+            eye,
+            brain,
+            stats: self.stats,
+            energy: self.energy,
+            state: self.state,
+        })
+    }
+}
+
+impl FoodSnapshot {
+    fn new(food: &Food) -> Self {
+        Self {
+            id: food.id,
+            position: [food.position.x, food.position.y],
+            nutrition: food.nutrition,
+            regrowth: food.regrowth,
+            dormant: food.dormant,
+        }
+    }
+
+    fn restore(self) -> Food {
+        Food {
+            id: self.id,
+            position: self.position.into(),
+            nutrition: self.nutrition,
+            regrowth: self.regrowth,
+            dormant: self.dormant,
+        }
+    }
+}
+
+impl PatchSnapshot {
+    fn new(patch: &FoodPatch) -> Self {
+        Self {
+            center: [patch.center.x, patch.center.y],
+            heading: patch.heading,
+        }
+    }
+
+    fn restore(self) -> FoodPatch {
+        FoodPatch {
+            center: self.center.into(),
+            heading: self.heading,
+        }
+    }
+}
+
+/// Describes why given `Snapshot` couldn't be restored
+#[derive(Clone, Debug, PartialEq)]
+pub enum SnapshotError {
+    /// Snapshot has been created by an incompatible version of this crate
+    UnsupportedVersion { found: u32 },
+
+    /// Snapshot's configuration is invalid
+    InvalidConfig(ConfigError),
+
+    /// Chromosome of one of the animals doesn't match its body and eye
+    InvalidBrain {
+        animal: usize,
+        expected: usize,
+        actual: usize,
+    },
+
+    /// Ditto, but for one of the predators
+    InvalidPredatorBrain {
+        predator: usize,
+        expected: usize,
+        actual: usize,
+    },
+
+    /// Brain of one of the animals has a shape that doesn't fit the
+    /// simulation (see: `NeuroevolutionConfig`)
+    InvalidBrainShape { animal: usize },
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnsupportedVersion { found } => write!(
+                f,
+                "unsupported snapshot version: {} (expected {})",
+                found, SNAPSHOT_VERSION
+            ),
+
+            Self::InvalidConfig(err) => write!(f, "snapshot contains invalid config: {}", err),
+
+            Self::InvalidBrain {
+                animal,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "animal #{} has {} genes, but its body and eye require {}",
+                animal, actual, expected
+            ),
+
+            Self::InvalidPredatorBrain {
+                predator,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "predator #{} has {} genes, but its body and eye require {}",
+                predator, actual, expected
+            ),
+
+            Self::InvalidBrainShape { animal } => write!(
+                f,
+                "animal #{} has a brain that doesn't fit the simulation",
+                animal
+            ),
+        }
+    }
+}
+
+impl error::Error for SnapshotError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::InvalidConfig(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BodyConfig, VisionChannel};
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    fn config() -> SimulationConfig {
+        SimulationConfig {
+            animals: 10,
+            foods: 50,
+            eat_range: 0.05,
+            generation_length: 30,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn restored_simulation_follows_the_same_trajectory() {
+        let mut rng = ChaCha8Rng::from_seed(Default::default());
+        let mut sim = Simulation::from_config(config(), &mut rng).unwrap();
+
+        for _ in 0..45 {
+            sim.step(&mut rng);
+        }
+
+        let mut restored = Simulation::restore(sim.snapshot()).unwrap();
+        let mut restored_rng = rng.clone();
+
+        assert_eq!(restored.snapshot(), sim.snapshot());
+
+        for _ in 0..45 {
+            sim.step(&mut rng);
+            restored.step(&mut restored_rng);
+        }
+
+        assert_eq!(restored.snapshot(), sim.snapshot());
+    }
+
+    #[test]
+    fn restores_predators() {
+        let mut rng = ChaCha8Rng::from_seed(Default::default());
+
+        let config = SimulationConfig {
+            predators: Some(Default::default()),
+            vision: vec![VisionChannel::Food, VisionChannel::Predators],
+            ..config()
+        };
+
+        let mut sim = Simulation::from_config(config, &mut rng).unwrap();
+
+        for _ in 0..45 {
+            sim.step(&mut rng);
+        }
+
+        let mut restored = Simulation::restore(sim.snapshot()).unwrap();
+        let mut restored_rng = rng.clone();
+
+        for _ in 0..45 {
+            sim.step(&mut rng);
+            restored.step(&mut restored_rng);
+        }
+
+        assert_eq!(restored.snapshot(), sim.snapshot());
+    }
+
+    #[test]
+    fn restores_evolvable_bodies() {
+        let mut rng = ChaCha8Rng::from_seed(Default::default());
+
+        let config = SimulationConfig {
+            body: Some(BodyConfig {
+                speed_max: Some(0.002..=0.008),
+                size: Some(0.5..=2.0),
+                ..Default::default()
+            }),
+            ..config()
+        };
+
+        let mut sim = Simulation::from_config(config, &mut rng).unwrap();
+
+        for _ in 0..45 {
+            sim.step(&mut rng);
+        }
+
+        let mut restored = Simulation::restore(sim.snapshot()).unwrap();
+        let mut restored_rng = rng.clone();
+
+        assert_eq!(
+            restored.world().animals()[3].body(),
+            sim.world().animals()[3].body()
+        );
+
+        for _ in 0..45 {
+            sim.step(&mut rng);
+            restored.step(&mut restored_rng);
+        }
+
+        assert_eq!(restored.snapshot(), sim.snapshot());
+    }
+
+    #[test]
+    fn rejects_unsupported_version() {
+        let mut rng = ChaCha8Rng::from_seed(Default::default());
+        let mut snapshot = Simulation::from_config(config(), &mut rng)
+            .unwrap()
+            .snapshot();
+
+        snapshot.version += 1;
+
+        assert_eq!(
+            Simulation::restore(snapshot).err(),
+            Some(SnapshotError::UnsupportedVersion {
+                found: SNAPSHOT_VERSION + 1
+            }),
+        );
+    }
+
+    #[test]
+    fn rejects_mismatched_brain() {
+        let mut rng = ChaCha8Rng::from_seed(Default::default());
+        let mut snapshot = Simulation::from_config(config(), &mut rng)
+            .unwrap()
+            .snapshot();
+
+        snapshot.config.eye_cells += 1;
+
+        assert!(matches!(
+            Simulation::restore(snapshot),
+            Err(SnapshotError::InvalidBrain { animal: 0, .. }),
+        ));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn survives_serialization() {
+        let mut rng = ChaCha8Rng::from_seed(Default::default());
+        let mut sim = Simulation::from_config(config(), &mut rng).unwrap();
+
+        for _ in 0..15 {
+            sim.step(&mut rng);
+        }
+
+        let snapshot = sim.snapshot();
+        let json = serde_json::to_string(&snapshot).unwrap();
+
+        assert_eq!(serde_json::from_str::<Snapshot>(&json).unwrap(), snapshot);
+    }
+}
@@ -0,0 +1,80 @@
+/// Summary of a single generation.
+///
+/// (in `EvolutionMode::SteadyState` there are no generations as such -
+/// there, a "generation" is just a period of `generation_length` steps,
+/// summarized by `Simulation::train()`.)
+#[derive(Clone, Debug, PartialEq)]
+pub struct Statistics {
+    crate generation: usize,
+    crate min_fitness: f32,
+    crate max_fitness: f32,
+    crate avg_fitness: f32,
+    crate best_fitness_ever: f32,
+    crate regressed: bool,
+    crate predators: Option<Box<Statistics>>,
+}
+
+impl Statistics {
+    /// Summarizes fitness of the given population; `best_fitness_ever`
+    /// and `regressed` are left for the caller to fill.
+    crate fn new(generation: usize, fitness: &[f32]) -> Self {
+        assert!(!fitness.is_empty());
+
+        let min_fitness = fitness.iter().copied().fold(f32::INFINITY, f32::min);
+        let max_fitness = fitness.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+        let avg_fitness = fitness.iter().sum::<f32>() / (fitness.len() as f32);
+
+        Self {
+            generation,
+            min_fitness,
+            max_fitness,
+            avg_fitness,
+            best_fitness_ever: max_fitness,
+            regressed: false,
+            predators: None,
+        }
+    }
+
+    /// Number of the generation this summary describes, starting from zero
+    pub fn generation(&self) -> usize {
+        self.generation
+    }
+
+    pub fn min_fitness(&self) -> f32 {
+        self.min_fitness
+    }
+
+    pub fn max_fitness(&self) -> f32 {
+        self.max_fitness
+    }
+
+    pub fn avg_fitness(&self) -> f32 {
+        self.avg_fitness
+    }
+
+    /// The best fitness seen so far, in this or any of the previous
+    /// generations
+    pub fn best_fitness_ever(&self) -> f32 {
+        self.best_fitness_ever
+    }
+
+    /// Whether the fittest bird of this generation is worse than the
+    /// fittest bird of some previous generation - i.e. whether the
+    /// evolution has lost a champion along the way.
+    ///
+    /// (since each generation lives in a freshly scattered world, this can
+    /// happen even with elitism enabled - but then it means that the
+    /// champion simply got unlucky, not that it's been lost.)
+    pub fn regressed(&self) -> bool {
+        self.regressed
+    }
+
+    /// Statistics of the predators' population, if there are predators
+    /// in the world.
+    ///
+    /// (predators evolve independently from birds, so they've got their
+    /// own fitness, best fitness ever etc.)
+    pub fn predators(&self) -> Option<&Statistics> {
+        self.predators.as_deref()
+    }
+}
@@ -0,0 +1,169 @@
+use nalgebra as na;
+use std::f32::consts::PI;
+
+/// Shape of our world.
+///
+/// All of the shapes span the same unit square (that's where foods and
+/// birds get spawned), they differ only in what happens at its edges.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum WorldTopology {
+    /// Opposite edges are glued together - a bird flying through the
+    /// right edge reappears on the left one, and can see (and eat)
+    /// foods lying "on the other side":
+    ///
+    /// -----------------
+    /// |               |
+    /// |%             @|
+    /// |               |
+    /// -----------------
+    ///
+    /// (here @ is actually pretty close to %.)
+    Torus,
+
+    /// Edges are walls - birds bounce off of them, as a billiard ball
+    /// would
+    Bounded,
+
+    /// There are no edges whatsoever - birds can fly as far as they
+    /// wish (although all the foods stay inside the unit square)
+    Infinite,
+}
+
+impl WorldTopology {
+    /// Returns the shortest vector leading from `from` to `to`.
+    crate fn displacement(
+        self,
+        from: na::Point2<f32>,
+        to: na::Point2<f32>,
+    ) -> na::Vector2<f32> {
+        let vec = to - from;
+
+        match self {
+            Self::Torus => na::Vector2::new(wrap_delta(vec.x), wrap_delta(vec.y)),
+            Self::Bounded | Self::Infinite => vec,
+        }
+    }
+
+    crate fn distance(self, a: na::Point2<f32>, b: na::Point2<f32>) -> f32 {
+        self.displacement(a, b).norm()
+    }
+
+    /// Brings given bird back into the world, after it has moved to
+    /// `position` (possibly flying past an edge).
+    crate fn confine(self, position: &mut na::Point2<f32>, rotation: &mut na::Rotation2<f32>) {
+        match self {
+            Self::Torus => {
+                position.x = na::wrap(position.x, 0.0, 1.0);
+                position.y = na::wrap(position.y, 0.0, 1.0);
+            }
+
+            Self::Bounded => {
+                if let Some((x, turned)) = reflect(position.x) {
+                    position.x = x;
+
+                    if turned {
+                        *rotation = na::Rotation2::new(PI - rotation.angle());
+                    }
+                }
+
+                if let Some((y, turned)) = reflect(position.y) {
+                    position.y = y;
+
+                    if turned {
+                        *rotation = na::Rotation2::new(-rotation.angle());
+                    }
+                }
+            }
+
+            Self::Infinite => {
+                //
+            }
+        }
+    }
+}
+
+impl Default for WorldTopology {
+    fn default() -> Self {
+        Self::Torus
+    }
+}
+
+/// Wraps a single coordinate of a displacement into `<-0.5, 0.5>`.
+fn wrap_delta(delta: f32) -> f32 {
+    delta - delta.round()
+}
+
+/// Mirrors given coordinate against the walls it's crossed, if any;
+/// returns the mirrored coordinate and whether the bird has turned back
+/// (i.e. whether it's bounced an odd number of times).
+fn reflect(coord: f32) -> Option<(f32, bool)> {
+    let coord = if coord < 0.0 {
+        -coord
+    } else if coord > 1.0 {
+        2.0 - coord
+    } else {
+        return None;
+    };
+
+    // Usually birds move by a tiny bit each step, so a single reflection
+    // is enough to get back inside
+    if (0.0..=1.0).contains(&coord) {
+        return Some((coord, true));
+    }
+
+    // ... but a really fast bird might've flown past the opposite wall as
+    // well, in which case it keeps bouncing back and forth - i.e. its
+    // coordinate repeats every 2 units
+    let coord = coord.rem_euclid(2.0);
+
+    if coord <= 1.0 {
+        Some((coord, true))
+    } else {
+        Some((2.0 - coord, false))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+    use test_case::test_case;
+
+    #[test_case(WorldTopology::Torus, 0.02)]
+    #[test_case(WorldTopology::Bounded, 0.98)]
+    #[test_case(WorldTopology::Infinite, 0.98)]
+    fn distance_across_edge(topology: WorldTopology, expected: f32) {
+        let actual = topology.distance(na::Point2::new(0.99, 0.5), na::Point2::new(0.01, 0.5));
+
+        assert_relative_eq!(actual, expected, epsilon = 1e-6);
+    }
+
+    #[test_case(WorldTopology::Torus, 0.002, 0.0)]
+    #[test_case(WorldTopology::Bounded, 0.998, PI)]
+    #[test_case(WorldTopology::Infinite, 1.002, 0.0)]
+    fn confine_past_right_edge(topology: WorldTopology, expected_x: f32, expected_rot: f32) {
+        let mut position = na::Point2::new(1.002, 0.5);
+        let mut rotation = na::Rotation2::new(0.0);
+
+        topology.confine(&mut position, &mut rotation);
+
+        assert_relative_eq!(position.x, expected_x, epsilon = 1e-6);
+        assert_relative_eq!(position.y, 0.5);
+        assert_relative_eq!(rotation.angle().abs(), expected_rot, epsilon = 1e-6);
+    }
+
+    #[test_case(3.3, 0.7, PI)] // three bounces
+    #[test_case(2.3, 0.3, 0.0)] // two bounces
+    #[test_case(-1.3, 0.7, 0.0)]
+    #[test_case(-2.4, 0.4, PI)]
+    fn bounces_many_times(x: f32, expected_x: f32, expected_rot: f32) {
+        let mut position = na::Point2::new(x, 0.5);
+        let mut rotation = na::Rotation2::new(0.0);
+
+        WorldTopology::Bounded.confine(&mut position, &mut rotation);
+
+        assert_relative_eq!(position.x, expected_x, epsilon = 1e-6);
+        assert_relative_eq!(rotation.angle().abs(), expected_rot, epsilon = 1e-6);
+    }
+}
@@ -1,35 +1,272 @@
+use nalgebra as na;
+
 use crate::animal::Animal;
-use crate::food::Food;
+use crate::food::{Food, FoodPatch};
+use crate::grid::SpatialGrid;
+use crate::id::Counter;
+use crate::{Genealogy, Placement, SimulationConfig};
 
 #[derive(Debug)]
 pub struct World {
     crate animals: Vec<Animal>,
+    crate predators: Vec<Animal>,
     crate foods: Vec<Food>,
+
+    /// Patches foods grow in (see: `FoodDistribution::Patches`)
+    crate patches: Vec<FoodPatch>,
+
+    /// Number of steps since the world has been created; unlike
+    /// simulation's age, it doesn't get reset with each generation, which
+    /// is what makes seasons work (see: `FoodConfig::seasons`)
+    crate clock: usize,
+
+    /// Family tree of the animals; it's also what hands out their ids
+    crate genealogy: Genealogy,
+
+    /// What hands out ids of the foods
+    crate food_ids: Counter,
 }
 
 impl World {
-    pub fn random(rng: &mut dyn rand::RngCore) -> Self {
-        let animals = (0..40).map(|_| Animal::random(rng)).collect();
+    pub fn random(config: &SimulationConfig, rng: &mut dyn rand::RngCore) -> Self {
+        let animals = (0..config.animals)
+            .map(|_| Animal::random(config, rng))
+            .collect();
+
+        Self::with_animals(animals, config, rng)
+    }
+
+    crate fn with_animals(
+        mut animals: Vec<Animal>,
+        config: &SimulationConfig,
+        rng: &mut dyn rand::RngCore,
+    ) -> Self {
+        let patches = match &config.food {
+            Some(food) => food.patches(rng),
+            None => Vec::new(),
+        };
+
+        let mut genealogy = Genealogy::default();
+        let mut food_ids = Counter::default();
+
+        Self::place_birds(config, &mut animals, rng);
+
+        let foods = Self::scatter_foods(config, &animals, &patches, &mut food_ids, rng);
+
+        let mut predators = match &config.predators {
+            Some(predators) => (0..predators.count)
+                .map(|_| Animal::random_predator(predators, rng))
+                .collect(),
+
+            None => Vec::new(),
+        };
+
+        Self::free_from_obstacles(config, &mut predators);
+
+        for animal in animals.iter_mut().chain(&mut predators) {
+            animal.id = genealogy.birth(None);
+        }
+
+        Self {
+            animals,
+            predators,
+            foods,
+            patches,
+            clock: 0,
+            genealogy,
+            food_ids,
+        }
+    }
+
+    /// Lays birds out according to `SimulationConfig::bird_placement`.
+    crate fn place_birds(
+        config: &SimulationConfig,
+        animals: &mut [Animal],
+        rng: &mut dyn rand::RngCore,
+    ) {
+        // Birds start at uniformly random places anyway (see:
+        // `Animal::new()`), so there's nothing else to do
+        if config.bird_placement != Placement::Uniform {
+            let positions = config
+                .bird_placement
+                .place(animals.len(), config.topology, &[], rng);
+
+            for (animal, position) in animals.iter_mut().zip(positions) {
+                animal.position = position;
+            }
+        }
+
+        Self::free_from_obstacles(config, animals);
+    }
 
-        let foods = (0..60).map(|_| Food::random(rng)).collect();
+    /// Moves animals that happen to lie inside obstacles (see:
+    /// `SimulationConfig::obstacles`) out of them.
+    crate fn free_from_obstacles(config: &SimulationConfig, animals: &mut [Animal]) {
+        if let Some(obstacles) = &config.obstacles {
+            for animal in animals {
+                animal.position = obstacles.push_out(animal.position);
+            }
+        }
+    }
+
+    /// Moves a bird that has just been born (see:
+    /// `EvolutionMode::SteadyState`) into a place picked by
+    /// `SimulationConfig::bird_placement`.
+    crate fn place_newborn(
+        config: &SimulationConfig,
+        animals: &mut [Animal],
+        id: usize,
+        rng: &mut dyn rand::RngCore,
+    ) {
+        if config.bird_placement == Placement::Uniform {
+            Self::free_from_obstacles(config, &mut animals[id..=id]);
+            return;
+        }
+
+        let position =
+            config
+                .bird_placement
+                .respawn(animals.len(), rng, |candidate, min_distance| {
+                    animals.iter().enumerate().all(|(other, animal)| {
+                        other == id
+                            || config.topology.distance(candidate, animal.position) >= min_distance
+                    })
+                });
 
-        // ^ Our algorithm allows for animals and foods to overlap, so
-        // | it's hardly ideal - but good enough for our purposes.
-        // |
-        // | A more complex solution could be based off of e.g.
-        // | Poisson disk sampling:
-        // |
-        // | https://en.wikipedia.org/wiki/Supersampling
-        // ---
+        animals[id].position = position;
 
-        Self { animals, foods }
+        Self::free_from_obstacles(config, &mut animals[id..=id]);
+    }
+
+    /// Scatters foods around the world, according to
+    /// `SimulationConfig::food_placement` (or `FoodConfig::distribution`,
+    /// if foods grow in patches).
+    crate fn scatter_foods(
+        config: &SimulationConfig,
+        animals: &[Animal],
+        patches: &[FoodPatch],
+        food_ids: &mut Counter,
+        rng: &mut dyn rand::RngCore,
+    ) -> Vec<Food> {
+        let positions = if patches.is_empty() {
+            let birds: Vec<_> = match config.food_placement {
+                Placement::PoissonDisk { .. } => {
+                    animals.iter().map(|animal| animal.position).collect()
+                }
+                _ => Vec::new(),
+            };
+
+            config
+                .food_placement
+                .place(config.foods, config.topology, &birds, rng)
+        } else {
+            let food = config
+                .food
+                .as_ref()
+                .expect("patches come from the food config");
+
+            (0..config.foods)
+                .map(|_| food.patch_position(patches, rng).unwrap())
+                .collect()
+        };
+
+        positions
+            .into_iter()
+            .map(|position| match &config.obstacles {
+                Some(obstacles) => obstacles.push_out(position),
+                None => position,
+            })
+            .map(|position| Food {
+                id: food_ids.food(),
+                ..match &config.food {
+                    Some(food) => food.grow(position, rng),
+                    None => Food::at(position),
+                }
+            })
+            .collect()
+    }
+
+    /// Moves food that has just been eaten into a new place, where it
+    /// regrows after `FoodConfig::regrowth_delay` steps.
+    crate fn regrow_food(
+        config: &SimulationConfig,
+        foods: &mut [Food],
+        patches: &[FoodPatch],
+        food_ids: &mut Counter,
+        grid: Option<&mut SpatialGrid>,
+        id: usize,
+        rng: &mut dyn rand::RngCore,
+    ) {
+        let position = match config
+            .food
+            .as_ref()
+            .and_then(|food| food.patch_position(patches, rng))
+        {
+            Some(position) => position,
+
+            None => {
+                let foods = &*foods;
+                let grid = grid.as_deref();
+
+                config.food_placement.respawn(
+                    foods.len(),
+                    rng,
+                    |candidate: na::Point2<f32>, min_distance| {
+                        let others = match grid {
+                            Some(grid) => grid.query(candidate, min_distance),
+                            None => (0..foods.len()).collect(),
+                        };
+
+                        others.into_iter().all(|other| {
+                            other == id
+                                || config.topology.distance(candidate, foods[other].position)
+                                    >= min_distance
+                        })
+                    },
+                )
+            }
+        };
+
+        let position = match &config.obstacles {
+            Some(obstacles) => obstacles.push_out(position),
+            None => position,
+        };
+
+        foods[id] = match &config.food {
+            Some(food) => Food {
+                id: food_ids.food(),
+                regrowth: food.regrowth_delay,
+                ..food.grow(position, rng)
+            },
+
+            None => Food {
+                id: food_ids.food(),
+                ..Food::at(position)
+            },
+        };
+
+        if let Some(grid) = grid {
+            grid.update(id, position);
+        }
     }
 
     pub fn animals(&self) -> &[Animal] {
         &self.animals
     }
 
+    /// Returns predators hunting the birds; empty, unless they've been
+    /// enabled through `SimulationConfig::predators`
+    pub fn predators(&self) -> &[Animal] {
+        &self.predators
+    }
+
     pub fn foods(&self) -> &[Food] {
         &self.foods
     }
+
+    /// Returns family tree of all the animals that have ever lived in
+    /// this world
+    pub fn genealogy(&self) -> &Genealogy {
+        &self.genealogy
+    }
 }